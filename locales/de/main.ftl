@@ -31,6 +31,8 @@ register = Registrieren
 display-name = Anzeigename
 email-already-registered = Es existiert bereits ein Admin mit dieser E-Mail!
 invalid-login = E-Mail oder Passwort falsch!
+too-many-requests = Zu viele Anfragen
+rate-limited = Es gab zu viele Versuche. Bitte warte eine Weile und versuche es dann erneut.
 please-select = Bitte auswählen
 continue = Weiter
 back = Zurück
@@ -41,6 +43,77 @@ delete = Löschen
 home = Zurück zur Startseite
 update = Aktualisieren
 logout = Logout
+sessions = Aktive Sitzungen
+sessions-explanation = Das sind alle Geräte, auf denen du gerade angemeldet bist. Beende jede Sitzung, die du nicht wiedererkennst.
+session-device = Gerät
+session-created = Angemeldet
+session-last-seen = Zuletzt aktiv
+session-current = dieses Gerät
+session-revoke = Abmelden
+session-revoke-all = Überall abmelden
+session-revoked = Die Sitzung wurde abgemeldet.
+audit-log = Protokoll
+audit-log-all-actions = Alle Aktionen
+audit-log-all-admins = Alle Admins
+audit-log-filter = Filtern
+audit-log-time = Zeit
+audit-log-admin = Admin
+audit-log-action = Aktion
+audit-log-target = Betroffenes Objekt
+audit-log-details = Details
+audit-log-no-admin = –
+audit-log-empty = Keine Einträge gefunden.
+audit-log-previous = Neuere Einträge
+audit-log-next = Ältere Einträge
+outbox = Ausgehende Mails
+outbox-explanation = Mails werden im Hintergrund verschickt. Mails, die nicht verschickt werden konnten, werden mit zunehmendem Abstand erneut versucht und nach mehreren Versuchen aufgegeben.
+outbox-empty = Alle Mails wurden verschickt.
+outbox-recipient = An
+outbox-subject = Betreff
+outbox-created = Erstellt
+outbox-attempts = Versuche
+outbox-status = Status
+outbox-pending = Nächster Versuch
+outbox-failed = Aufgegeben
+outbox-last-error = Letzter Fehler
+outbox-retry = Jetzt erneut versuchen
+outbox-retry-failed = Alle aufgegebenen Mails erneut versuchen
+outbox-delete = Löschen
+outbox-retried = Die Mails werden in Kürze erneut verschickt.
+outbox-deleted = Die Mail wurde gelöscht.
+export = Export
+export-explanation = Lade die Termine und ihre Buchungen als CSV-Datei herunter, die mit Tabellenkalkulationen geöffnet werden kann. Leere Felder filtern die Termine nicht.
+export-all-date-types = Alle Ensembles
+export-from-day = Ab Tag
+export-to-day = Bis Tag
+export-status = Status
+export-all = Alle
+export-booked = Gebucht
+export-free = Frei
+export-separator = Trennzeichen
+export-separator-comma = Komma
+export-separator-semicolon = Semikolon (z. B. für Excel mit deutschen Einstellungen)
+export-language = Sprache
+export-download = Herunterladen
+two-factor = Zwei-Faktor-Authentifizierung
+two-factor-code = Code aus der App oder Wiederherstellungscode
+two-factor-login-explanation = Bitte gib den Code aus deiner Authenticator-App oder einen deiner Wiederherstellungscodes ein.
+two-factor-invalid-code = Der Code ist ungültig oder wurde bereits verwendet.
+two-factor-enroll-explanation = Scanne den QR-Code mit einer Authenticator-App oder gib das Geheimnis von Hand ein. Bestätige danach mit dem Code, den die App anzeigt.
+two-factor-open-app = In der Authenticator-App öffnen
+two-factor-secret = Geheimnis
+two-factor-enable = Aktivieren
+two-factor-enabled = Die Zwei-Faktor-Authentifizierung ist jetzt aktiviert.
+two-factor-active = Die Zwei-Faktor-Authentifizierung ist aktiviert. Du hast noch { $count } unbenutzte Wiederherstellungscodes.
+two-factor-disable = Deaktivieren
+two-factor-disabled = Die Zwei-Faktor-Authentifizierung wurde deaktiviert.
+two-factor-cannot-disable = Die Zwei-Faktor-Authentifizierung ist verpflichtend und kann nicht deaktiviert werden.
+two-factor-renew-recovery-codes = Neue Wiederherstellungscodes erstellen
+two-factor-recovery-codes-explanation = Das sind deine Wiederherstellungscodes. Jeder davon kann einmal anstelle eines Codes aus der App verwendet werden. Bewahre sie sicher auf, sie werden nicht erneut angezeigt.
+two-factor-required-info = Die Zwei-Faktor-Authentifizierung ist verpflichtend. Bitte richte sie ein, bevor du weitermachst.
+two-factor-settings = Einstellungen für alle Admins
+two-factor-require = Zwei-Faktor-Authentifizierung verpflichtend machen
+two-factor-settings-saved = Die Einstellungen wurden gespeichert.
 
 validation-password = Das Passwort muss mindestens acht deutsche Zeichen enthalten, davon ein Kleinbuchstabe, ein Großbuchstabe, eine Ziffer und ein Sonderzeichen.
 validation-email = Die E-Mail-Adresse ist ungültig!
@@ -48,14 +121,63 @@ validation-display-name = Der Anzeigename muss mindestens zwei deutsche Zeichen
 validation-unknown = Ungültige Eingabe!
 validation-date = Ungültiges Datum!
 validation-date-ambiguous = Das eingegebene Datum ist nicht eindeutig. Dies kann z.B. durch die Zeitumstellung passieren.
+validation-weekdays = Bitte wähle mindestens einen Wochentag aus.
+validation-times = Bitte gib die Zeiten als Zeiträume wie „10:00-13:00“ in zeitlicher Reihenfolge und ohne Überschneidungen an.
+validation-slot-minutes = Die Zeit pro Termin muss zwischen einer Minute und 24 Stunden liegen.
+validation-buffer-minutes = Die Pause zwischen den Terminen darf nicht länger als 24 Stunden sein.
+validation-schedule-template-name = Bitte gib einen Namen für die Zeitplanvorlage an.
 validation-room = Unbekannter Raum!
 validation-select = Bitte einen Eintrag wählen!
+validation-identifier = Erlaubt sind nur Kleinbuchstaben, Ziffern und Bindestriche.
+validation-display-names = Bitte gib für jede Sprache einen Namen an.
+validation-reminder-hours = Bitte gib ganze Stundenzahlen zwischen 1 und 720 durch Kommas getrennt an.
 
 invite = Admin einladen
 invite-action = Einladen
 invite-successful = Einladung erfolgreich versendet!
 initial-registration-info = Es ist noch kein Admin registriert, erlaube initiale Einladung.
 invite-invalid-token = Invalides Einladungs-Token!
+link-expired = Link abgelaufen
+invite-expired = Diese Einladung ist abgelaufen. Bitte lass dich von einem Admin erneut einladen.
+role = Rolle
+role-owner = Eigentümer
+role-organizer = Organisation
+role-jury = Jury-Mitglied
+role-viewer = Nur lesen
+invite-date-types = Zugriff auf folgende Ensembles beschränken. Wenn keines ausgewählt ist, hat der Admin Zugriff auf alle Ensembles.
+forbidden = Zugriff verweigert
+forbidden-explanation = Du hast nicht die Berechtigung, diese Aktion auszuführen.
+admins = Admins verwalten
+admin-you = du
+admin-all-date-types = alle
+last-login = Letzter Login
+admin-never = nie
+admin-delete-confirmation = Willst du diesen Admin wirklich löschen?
+admin-deleted = Der Admin wurde gelöscht.
+admin-delete-last = Der letzte Admin kann nicht gelöscht werden.
+admin-delete-last-owner = Der letzte Eigentümer kann nicht gelöscht werden.
+admin-pending-invites = Offene Einladungen
+admin-no-pending-invites = Es gibt keine offenen Einladungen.
+admin-invited = Eingeladen
+invite-resend = Erneut senden
+invite-resent = Die Einladung wurde erneut verschickt.
+invite-revoke = Zurückziehen
+invite-revoked = Die Einladung wurde zurückgezogen.
+profile = Profil
+current-password = Aktuelles Passwort
+current-password-wrong = Das aktuelle Passwort ist falsch!
+password-change = Passwort ändern
+password-changed = Dein Passwort wurde geändert. Alle anderen Geräte wurden abgemeldet.
+profile-updated = Dein Profil wurde aktualisiert.
+calendar-subscription = Kalenderabonnement
+calendar-subscription-explanation = Abonniere diese Adressen in deiner Kalender-App, um die Vorsing- / Vorspieltermine dort zu sehen, zusammen mit den Personen, die sie gebucht haben. Jede Person, die eine Adresse kennt, kann die Termine sehen, also halte sie bitte geheim.
+calendar-subscription-all = Alle Ensembles
+calendar-subscription-disabled = Das Kalenderabonnement ist deaktiviert.
+calendar-subscription-enable = Aktivieren
+calendar-subscription-renew = Neue Adressen erstellen
+calendar-subscription-disable = Deaktivieren
+calendar-subscription-renewed = Es wurden neue Adressen erstellt. Die bisherigen Adressen funktionieren nicht mehr.
+calendar-subscription-turned-off = Das Kalenderabonnement wurde deaktiviert.
 
 mail-invite-subject = Einladung zur Verwaltung der Vorsingtermine
 mail-invite-body = Du wurdest zur Verwaltung der Vorsingtermine eingeladen. Bitte besuche zur Registrierung folgenden Link:
@@ -72,6 +194,7 @@ password-reset-action = Passwort zurücksetzen
 password-reset-sent = E-Mail zum Zurücksetzen des Passworts verschickt.
 new-password = Neues Passwort
 password-reset-invalid = Invalider Link zum Zurücksetzen des Passworts!
+password-reset-expired = Dieser Link zum Zurücksetzen des Passworts ist abgelaufen. Bitte fordere einen neuen an.
 
 mail-password-reset-subject = Passwort zurücksetzen
 mail-password-reset-body = Für deinen Account zur Verwaltung der Vorsingtermine wurde ein Zurücksetzen des Passworts beantragt. Bitte besuche hierfür folgenden Link:
@@ -86,18 +209,118 @@ from-date = Von
 to-date = Bis
 interval = Zeit pro Termin in Minuten
 wrong-date-order = Das Von-Datum muss vor dem Bis-Datum liegen.
-interval-not-even = Die Zeit zwischen dem Von- und Bis-Datum muss sich durch das Intervall teilen lassen.
+from-day = Ab Tag
+to-day = Bis Tag
+weekdays = Wochentage
+weekday-1 = Montag
+weekday-2 = Dienstag
+weekday-3 = Mittwoch
+weekday-4 = Donnerstag
+weekday-5 = Freitag
+weekday-6 = Samstag
+weekday-7 = Sonntag
+times = Zeiten
+times-explanation = Die Zeiten an jedem Tag, durch Kommas getrennt, z. B. „10:00-13:00, 14:00-18:00“. Die Lücken dazwischen sind Pausen, etwa eine Mittagspause.
+buffer-minutes = Pause zwischen zwei Terminen in Minuten
+no-dates-generated = In die angegebenen Zeiten passt kein Termin.
+date-conflict-existing = Überschneidet sich mit { $datetype } am
+date-conflict-booked = gebucht
+date-conflict-new = Überschneidet sich mit einem anderen neuen Termin im selben Raum.
+date-conflicts = Einige Termine überschneiden sich mit anderen Terminen im selben Raum. Bitte wähle sie ab.
+schedule-template = Zeitplanvorlage
+schedule-template-apply = Übernehmen
+schedule-templates-manage = Zeitplanvorlagen verwalten
+schedule-templates = Zeitplanvorlagen
+schedule-templates-explanation = Zeitplanvorlagen speichern Zeiten und Terminlängen, die dann beim Erstellen von Terminen übernommen werden können.
+schedule-templates-empty = Es gibt noch keine Zeitplanvorlagen.
+schedule-template-name = Name
+schedule-template-new = Neue Zeitplanvorlage
+schedule-template-created = Die Zeitplanvorlage wurde gespeichert.
+schedule-template-deleted = Die Zeitplanvorlage wurde gelöscht.
+schedule-template-name-taken = Es gibt bereits eine Zeitplanvorlage mit diesem Namen.
 too-many-dates = Die angegebenen Daten würden zu mehr als 1000 Terminen führen, was nicht erlaubt ist.
+date-import = Termine importieren
+date-import-explanation = Lade eine CSV-Datei mit einem Termin pro Zeile und den Spalten Ensemble, Raum, Beginn und Ende hoch. Die erste Zeile enthält die Spaltennamen und wird übersprungen. Ensembles können mit ihrem Namen oder ihrer Kennung angegeben werden, Zeiten als „16.03.2024 10:00“ oder „2024-03-16 10:00“ in der lokalen Zeitzone. Als Trennzeichen können Kommas oder Semikolons verwendet werden.
+date-import-example = { "" }Ensemble;Raum;Beginn;Ende
+    Chor;A1;16.03.2024 10:00;16.03.2024 10:15
+    Chor;A1;16.03.2024 10:20;16.03.2024 10:35
+date-import-file = CSV-Datei
+date-import-invalid = Die Datei ist leer oder keine CSV-Datei. Bitte speichere sie als CSV mit UTF-8-Kodierung.
+date-import-row = Zeile
+date-import-columns = Die Zeile muss genau vier Spalten haben.
+date-import-date-type = Unbekanntes Ensemble.
+date-import-datetime = Beginn oder Ende können nicht gelesen werden.
+date-import-overlap-existing = Der Termin überschneidet sich mit einem bestehenden Termin im selben Raum.
+date-import-overlap-file = Der Termin überschneidet sich mit einem Termin weiter oben im selben Raum.
+date-import-again = Andere Datei hochladen
+publish-at = Veröffentlicht ab
+publish-at-explanation = Leer lassen, um die Termine sofort zu veröffentlichen. Die Warteliste wird benachrichtigt, sobald die Termine veröffentlicht sind.
 room-manage = Räume erstellen und löschen
 room-deleted = Raum gelöscht.
 room-created = Raum erstellt.
 announcements-manage = Ankündigungen verwalten
+date-types-manage = Ensembles verwalten
+date-types-explanation = Jedes Ensemble bekommt eine eigene Buchungsseite und Ankündigung. Ensembles mit Vorsingterminen können nicht gelöscht werden.
+identifier = Kennung
+identifier-explanation = Wird in Links wie /dates/big-band verwendet. Erlaubt sind nur Kleinbuchstaben, Ziffern und Bindestriche.
+sort-order = Reihenfolge
+edit = Bearbeiten
+date-type-dates = Termine
+date-type-exists = Es gibt bereits ein Ensemble mit dieser Kennung.
+date-type-created = Ensemble erstellt.
+date-type-updated = Ensemble aktualisiert.
+date-type-deleted = Ensemble gelöscht.
+date-type-in-use = Dieses Ensemble hat noch Vorsingtermine und kann nicht gelöscht werden.
+date-type-has-admins = Admins oder Einladungen sind auf dieses Ensemble beschränkt. Bitte ändere zuerst ihre Ensembles, bevor du es löschst.
+date-type-delete-confirmation = Willst du dieses Ensemble mit seinen Stimmen, Ankündigungen und seiner Warteliste wirklich löschen?
+voices-booking = Stimmen für die Buchung
+voices-result = Stimmen für Ergebnisse
+voice-position = Verwendet für
+voice-position-booking = Buchung
+voice-position-result = Ergebnis
+voice-new = Stimme hinzufügen
+voice-bookings = Buchungen
+voice-exists = Diese Stimme gibt es bereits.
+voice-created = Stimme erstellt.
+voice-updated = Stimme aktualisiert.
+voice-deleted = Stimme gelöscht.
+voice-in-use = Diese Stimme wurde bereits gebucht und kann nicht gelöscht werden.
+date-type-settings = Buchungseinstellungen
+date-type-enabled = Auf der Startseite anzeigen und Buchungen erlauben
+date-type-opens-at = Buchung möglich ab
+date-type-application-deadline = Bewerbungsschluss
+date-type-application-deadline-explanation = Nach dem Bewerbungsschluss können nur noch Personen Termine buchen, die sich vorher auf die Warteliste eingetragen haben.
+date-type-days-deadline = Tage vor einem Termin, bis zu denen er gebucht werden kann
+date-type-dates-per-day = Angebotene Termine pro Tag
+date-type-dates-per-day-explanation = Bei 0 werden alle freien Termine eines Tages angeboten.
+date-type-reminder-hours = Erinnerungsmails (Stunden vor dem Termin)
+date-type-reminder-hours-explanation = Durch Kommas getrennt, z.B. "48, 2". Leer lassen, um keine Erinnerungen zu verschicken.
+date-type-settings-saved = Die Buchungseinstellungen wurden gespeichert.
+opens-after-deadline = Die Buchung muss vor dem Bewerbungsschluss beginnen.
+announcement-date-type-description = Ankündigung, die auf der Seite angezeigt wird, wo Termine für { $date_type } angezeigt werden. Dieser Text wird auch in der Bestätigungs-Mail angezeigt.
 date-cancel = Termin stornieren
 date-cancel-explanation = Bitte gebe einen Text an, der an die Personen per Mail verschickt wird, die bereits einen der Termine gebucht haben. Wenn alle Felder leer bleiben, wird keine Mail verschickt.
 no-dates-selected = Es sind keine Termine ausgewählt worden.
 
+booking-result = Ergebnis eintragen
+result-outcome = Ergebnis
+result-accepted = Angenommen
+result-rejected = Abgelehnt
+result-waitlisted = Warteliste
+result-voice = Zugewiesene Stimme
+result-no-voice = Keine Stimme zugewiesen
+result-notes = Notizen der Jury (privat)
+result-already-mailed = Das Ergebnis wurde bereits an die Person verschickt. Wenn du das Ergebnis oder die Stimme änderst, wird es mit den nächsten Ergebnis-Mails erneut verschickt.
+result-mail = Ergebnis-Mails verschicken
+result-mail-explanation = Verschickt an alle Personen, für die ein Ergebnis eingetragen wurde, eine E-Mail mit der Zu- oder Absage. Jede Person wird nur einmal angeschrieben, sodass der Versand nach einem Fehler gefahrlos wiederholt werden kann.
+result-mail-pending = { $datetype }: { $count } Ergebnisse noch nicht verschickt.
+result-mail-action = Mails verschicken
+result-mail-none = Es gibt keine Ergebnisse, die noch nicht verschickt wurden.
+result-mail-sent = Ergebnis-Mails erfolgreich verschickt.
+
 welcome-user = Willkommen auf unserer Webseite für die Vorsingen!
 no-dates = Derzeit sind keine Termine verfügbar. Bitte trage dich unten in die Warteliste ein, um benachrichtigt zu werden, wenn neue Termine angeboten werden.
+booking-opens-at = Die Buchung ist möglich ab
 waiting-list = Warteliste { $datetype }
 waiting-list-new-dates = Warteliste für neue Termine
 waiting-list-enter = Eintragen
@@ -125,7 +348,38 @@ mail-booking-body = Dein Termin wurde erfolgreich gebucht. Wir freuen uns auf di
     Raum: { $room }
 
     Falls du nicht kommen kannst, bitten wir dich dringend den Termin wieder zu stornieren, damit andere ihn buchen können. Folge dazu bitte diesem Link:
+mail-reminder-subject = Erinnerung an deinen Vorsingtermin
+mail-reminder-body = Wir möchten dich an deinen bevorstehenden Termin erinnern. Wir freuen uns auf dich!
+
+    Tag:  { $day }
+    Zeit: { $from } - { $to }
+    Raum: { $room }
+
+    Falls du nicht kommen kannst, bitten wir dich dringend den Termin wieder zu stornieren, damit andere ihn buchen können. Folge dazu bitte diesem Link:
+mail-booking-cancelled-subject = Buchung des Vorsing- / Vorspieltermins storniert
+mail-booking-cancelled-body = Deine Buchung des folgenden Vorsing- / Vorspieltermins wurde storniert:
+
+    Tag:  { $day }
+    Zeit: { $from } - { $to }
+    Raum: { $room }
 mail-date-cancel-subject = Vorsingen / Vorspiel storniert
+calendar-summary = Vorsingen / Vorspiel: { $datetype }
+calendar-feed-name = Vorsing- / Vorspieltermine
+calendar-feed-name-date-type = Vorsing- / Vorspieltermine: { $datetype }
+calendar-feed-booked = { $datetype }: { $name } ({ $voice })
+calendar-feed-free = { $datetype }: nicht gebucht
 mail-waiting-list-confirmation = Du wurdest erfoglreich in unsere Warteliste für neue Vorsingtermine / Vorspieltermine eingetragen.
 mail-waiting-list-invite = Es sind neue Vorsingtermine / Vorspieltermine verfügbar. Besuche bitte unsere Webseite um dich anzumelden:
 mail-waiting-list-footer = Um dich aus der Warteliste auszutragen, besuche bitte folgenden Link:
+mail-unsubscribe = Warteliste verlassen
+mail-result-subject = Dein Ergebnis für { $datetype }
+mail-result-accepted-body = Hallo { $name },
+
+    vielen Dank für dein Vorsingen / Vorspielen. Wir freuen uns, dir mitteilen zu können, dass du in den { $datetype } aufgenommen wurdest!
+mail-result-voice = Du wirst in folgender Stimme singen / spielen: { $voice }
+mail-result-rejected-body = Hallo { $name },
+
+    vielen Dank für dein Vorsingen / Vorspielen. Leider können wir dir dieses Mal keinen Platz im { $datetype } anbieten.
+mail-result-waitlisted-body = Hallo { $name },
+
+    vielen Dank für dein Vorsingen / Vorspielen. Wir haben dich auf unsere Warteliste für den { $datetype } gesetzt und melden uns, sobald ein Platz frei wird.
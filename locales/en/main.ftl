@@ -31,6 +31,8 @@ register = Register
 display-name = Display name
 email-already-registered = This email is already registered as an admin!
 invalid-login = E-Mail or password wrong!
+too-many-requests = Too many requests
+rate-limited = There were too many attempts. Please wait a while and try again.
 please-select = Please select
 continue = Continue
 back = Back
@@ -41,6 +43,77 @@ delete = Delete
 home = Back to the start page
 update = Update
 logout = Logout
+sessions = Active sessions
+sessions-explanation = These are all devices on which you are currently logged in. Revoke any session you do not recognize.
+session-device = Device
+session-created = Logged in
+session-last-seen = Last active
+session-current = this device
+session-revoke = Log out
+session-revoke-all = Log out everywhere
+session-revoked = The session has been logged out.
+audit-log = Audit log
+audit-log-all-actions = All actions
+audit-log-all-admins = All admins
+audit-log-filter = Filter
+audit-log-time = Time
+audit-log-admin = Admin
+audit-log-action = Action
+audit-log-target = Affected object
+audit-log-details = Details
+audit-log-no-admin = –
+audit-log-empty = No entries found.
+audit-log-previous = Newer entries
+audit-log-next = Older entries
+outbox = Outgoing mails
+outbox-explanation = Mails are sent in the background. Mails which could not be sent are retried with increasing delays and given up after several attempts.
+outbox-empty = All mails have been sent.
+outbox-recipient = Recipient
+outbox-subject = Subject
+outbox-created = Created
+outbox-attempts = Attempts
+outbox-status = Status
+outbox-pending = Next attempt
+outbox-failed = Given up
+outbox-last-error = Last error
+outbox-retry = Retry now
+outbox-retry-failed = Retry all given up mails
+outbox-delete = Delete
+outbox-retried = The mails will be sent again shortly.
+outbox-deleted = The mail has been deleted.
+export = Export
+export-explanation = Download the dates and their bookings as a CSV file, which can be opened with spreadsheet applications. Empty fields don't filter the dates.
+export-all-date-types = All ensembles
+export-from-day = From day
+export-to-day = To day
+export-status = Status
+export-all = All
+export-booked = Booked
+export-free = Free
+export-separator = Separator
+export-separator-comma = Comma
+export-separator-semicolon = Semicolon (e.g. for Excel with German settings)
+export-language = Language
+export-download = Download
+two-factor = Two-factor authentication
+two-factor-code = Code from the app or recovery code
+two-factor-login-explanation = Please enter the code from your authenticator app or one of your recovery codes.
+two-factor-invalid-code = The code is invalid or has already been used.
+two-factor-enroll-explanation = Scan the QR code with an authenticator app or enter the secret manually. Then confirm with the code shown in the app.
+two-factor-open-app = Open in authenticator app
+two-factor-secret = Secret
+two-factor-enable = Enable
+two-factor-enabled = Two-factor authentication is now enabled.
+two-factor-active = Two-factor authentication is enabled. You have { $count } unused recovery codes left.
+two-factor-disable = Disable
+two-factor-disabled = Two-factor authentication has been disabled.
+two-factor-cannot-disable = Two-factor authentication is mandatory and cannot be disabled.
+two-factor-renew-recovery-codes = Create new recovery codes
+two-factor-recovery-codes-explanation = These are your recovery codes. Each of them can be used once instead of a code from the app. Store them in a safe place, they will not be shown again.
+two-factor-required-info = Two-factor authentication is mandatory. Please set it up before you continue.
+two-factor-settings = Settings for all admins
+two-factor-require = Make two-factor authentication mandatory
+two-factor-settings-saved = The settings have been saved.
 
 validation-password = Passwords must contain at least eight German, one lower case, one upper case, one digit and one special character!
 validation-email = E-Mail must be a well-formed e-mail address.
@@ -48,14 +121,63 @@ validation-display-name = The display name must contain at least two German char
 validation-unknown = Invalid entry!
 validation-date = Invalid date!
 validation-date-ambiguous = The entered date is not unique. This can be caused by the summer / winter time transition.
+validation-weekdays = Please select at least one weekday.
+validation-times = Please enter the times as ranges like "10:00-13:00" in chronological order, which don't overlap.
+validation-slot-minutes = The time per date must be between one minute and 24 hours.
+validation-buffer-minutes = The break between dates must not be longer than 24 hours.
+validation-schedule-template-name = Please enter a name for the schedule template.
 validation-room = Unknown room!
 validation-select = Please select an entry.
+validation-identifier = Only lowercase letters, digits and dashes are allowed.
+validation-display-names = Please enter a name for every language.
+validation-reminder-hours = Please enter whole numbers of hours between 1 and 720, separated by commas.
 
 invite = Invite admin
 invite-action = Invite
 invite-successful = Invite sent successfully!
 initial-registration-info = There is no admin registered, yet. Allowing initial registration.
 invite-invalid-token = Invalid invite token!
+link-expired = Link expired
+invite-expired = This invite has expired. Please ask an admin to invite you again.
+role = Role
+role-owner = Owner
+role-organizer = Organizer
+role-jury = Jury member
+role-viewer = Read-only
+invite-date-types = Restrict access to the following ensembles. If none is selected, the admin may access all ensembles.
+forbidden = Access denied
+forbidden-explanation = You do not have the permission to perform this action.
+admins = Manage admins
+admin-you = you
+admin-all-date-types = all
+last-login = Last login
+admin-never = never
+admin-delete-confirmation = Do you really want to delete this admin?
+admin-deleted = The admin has been deleted.
+admin-delete-last = The last admin cannot be deleted.
+admin-delete-last-owner = The last owner cannot be deleted.
+admin-pending-invites = Pending invites
+admin-no-pending-invites = There are no pending invites.
+admin-invited = Invited
+invite-resend = Resend
+invite-resent = The invite has been sent again.
+invite-revoke = Revoke
+invite-revoked = The invite has been revoked.
+profile = Profile
+current-password = Current password
+current-password-wrong = The current password is wrong!
+password-change = Change password
+password-changed = Your password has been changed. All other devices have been logged out.
+profile-updated = Your profile has been updated.
+calendar-subscription = Calendar subscription
+calendar-subscription-explanation = Subscribe to these addresses in your calendar app to see the audition dates there, including the candidates who booked them. Anyone who knows an address can see the dates, so please keep them secret.
+calendar-subscription-all = All ensembles
+calendar-subscription-disabled = The calendar subscription is disabled.
+calendar-subscription-enable = Enable
+calendar-subscription-renew = Create new addresses
+calendar-subscription-disable = Disable
+calendar-subscription-renewed = New addresses have been created. The previous addresses don't work anymore.
+calendar-subscription-turned-off = The calendar subscription has been disabled.
 
 mail-invite-subject = Invitation to audition dates administration
 mail-invite-body = You were invited to the administration of the audition dates. Please visit the following link for registration:
@@ -72,6 +194,7 @@ password-reset-action = Reset password
 password-reset-sent = E-Mail for password reset sent successfully.
 new-password = New password
 password-reset-invalid = Invalid link for password reset!
+password-reset-expired = This link for resetting your password has expired. Please request a new one.
 
 mail-password-reset-subject = Password reset
 mail-password-reset-body = Someone requested a reset of your password for the administration of the audition dates. Please visit the following link to reset your password:
@@ -86,18 +209,118 @@ from-date = From
 to-date = To
 interval = Time per date in minutes
 wrong-date-order = The from date must be before the to date.
-interval-not-even = The time between the from and to date must be divisible by the interval.
+from-day = From day
+to-day = To day
+weekdays = Weekdays
+weekday-1 = Monday
+weekday-2 = Tuesday
+weekday-3 = Wednesday
+weekday-4 = Thursday
+weekday-5 = Friday
+weekday-6 = Saturday
+weekday-7 = Sunday
+times = Times
+times-explanation = The times on each day, separated by commas, e.g. "10:00-13:00, 14:00-18:00". The gaps between them are breaks, like a lunch break.
+buffer-minutes = Break between two dates in minutes
+no-dates-generated = No date fits into the entered times.
+date-conflict-existing = Overlaps with { $datetype } on
+date-conflict-booked = booked
+date-conflict-new = Overlaps with another new date in the same room.
+date-conflicts = Some dates overlap with other dates in the same room. Please deselect them.
+schedule-template = Schedule template
+schedule-template-apply = Apply
+schedule-templates-manage = Manage schedule templates
+schedule-templates = Schedule templates
+schedule-templates-explanation = Schedule templates save times and date lengths, which can then be applied when creating dates.
+schedule-templates-empty = There are no schedule templates yet.
+schedule-template-name = Name
+schedule-template-new = New schedule template
+schedule-template-created = The schedule template has been saved.
+schedule-template-deleted = The schedule template has been deleted.
+schedule-template-name-taken = There already is a schedule template with this name.
 too-many-dates = The entered data would lead to more than 1000 dates, which is not allowed.
+date-import = Import dates
+date-import-explanation = Upload a CSV file with one date per line and the columns ensemble, room, start and end. The first line contains the column names and is skipped. Ensembles can be given by their name or identifier, times are given as "2024-03-16 10:00" or "16.03.2024 10:00" in the local time zone. Commas or semicolons can be used as separator.
+date-import-example = { "" }Ensemble,Room,Start,End
+    Choir,A1,2024-03-16 10:00,2024-03-16 10:15
+    Choir,A1,2024-03-16 10:20,2024-03-16 10:35
+date-import-file = CSV file
+date-import-invalid = The file is empty or no CSV file. Please save it as CSV with UTF-8 encoding.
+date-import-row = Row
+date-import-columns = The row must have exactly four columns.
+date-import-date-type = Unknown ensemble.
+date-import-datetime = The start or end can't be read.
+date-import-overlap-existing = The date overlaps an existing date in the same room.
+date-import-overlap-file = The date overlaps a date above in the same room.
+date-import-again = Upload another file
+publish-at = Published at
+publish-at-explanation = Leave empty to publish the dates immediately. The waiting list is notified once the dates are published.
 room-manage = Delete and create rooms
 room-deleted = Room deleted.
 room-created = Room created.
 announcements-manage = Manage announcements
+date-types-manage = Manage ensembles
+date-types-explanation = Every ensemble gets its own booking page and announcement. Ensembles with audition dates can't be deleted.
+identifier = Identifier
+identifier-explanation = Used in links such as /dates/big-band. Only lowercase letters, digits and dashes are allowed.
+sort-order = Order
+edit = Edit
+date-type-dates = Dates
+date-type-exists = An ensemble with this identifier already exists.
+date-type-created = Ensemble created.
+date-type-updated = Ensemble updated.
+date-type-deleted = Ensemble deleted.
+date-type-in-use = This ensemble still has audition dates and can't be deleted.
+date-type-has-admins = Admins or invites are restricted to this ensemble. Please change their ensembles before deleting it.
+date-type-delete-confirmation = Do you really want to delete this ensemble together with its voices, announcements and waiting list?
+voices-booking = Voices for booking
+voices-result = Voices for results
+voice-position = Used for
+voice-position-booking = Booking
+voice-position-result = Result
+voice-new = Add voice
+voice-bookings = Bookings
+voice-exists = This voice already exists.
+voice-created = Voice created.
+voice-updated = Voice updated.
+voice-deleted = Voice deleted.
+voice-in-use = This voice has already been booked and can't be deleted.
+date-type-settings = Booking settings
+date-type-enabled = Show on the start page and allow bookings
+date-type-opens-at = Booking opens at
+date-type-application-deadline = Application deadline
+date-type-application-deadline-explanation = After the deadline, only people who entered the waiting list before it can book dates.
+date-type-days-deadline = Days before a date until which it can be booked
+date-type-dates-per-day = Dates offered per day
+date-type-dates-per-day-explanation = 0 offers all free dates of a day.
+date-type-reminder-hours = Reminder mails (hours before the date)
+date-type-reminder-hours-explanation = Comma-separated, e.g. "48, 2". Leave empty to send no reminders.
+date-type-settings-saved = The booking settings have been saved.
+opens-after-deadline = Booking has to open before the application deadline.
+announcement-date-type-description = Announcement which is shown on the page where one can book audition dates for { $date_type }. This text is also shown in the confirmation mail.
 date-cancel = Cancel date
 date-cancel-explanation = Please provide a text which is sent per mail to the people who already booked one of the dates. If all fields remain empty, no mail is sent.
 no-dates-selected = No dates were selected.
 
+booking-result = Record result
+result-outcome = Result
+result-accepted = Accepted
+result-rejected = Rejected
+result-waitlisted = Waiting list
+result-voice = Assigned voice
+result-no-voice = No voice assigned
+result-notes = Jury notes (private)
+result-already-mailed = The result has already been sent to the candidate. If you change the outcome or the voice, it will be sent again with the next result mails.
+result-mail = Send result mails
+result-mail-explanation = Sends each candidate whose result has been recorded an e-mail with the acceptance or rejection. Every candidate is mailed only once, so you can safely repeat this after an error.
+result-mail-pending = { $datetype }: { $count } results not mailed yet.
+result-mail-action = Send mails
+result-mail-none = There are no results which have not been mailed yet.
+result-mail-sent = Result mails sent successfully.
+
 welcome-user = Welcome to our webpage for the audition dates!
 no-dates = No dates available. Please enter the waiting list below to be notified if we add new dates.
+booking-opens-at = Booking opens at
 waiting-list = Waiting List { $datetype }
 waiting-list-new-dates = Waiting list for new dates
 waiting-list-enter = Subscribe
@@ -124,7 +347,38 @@ mail-booking-body = Your audition date was booked successfully. We are looking f
     Room: { $room }
 
     If you cannot come, we urgently ask you to cancel your date. To do so, please follow this link:
+mail-reminder-subject = Reminder of your audition date
+mail-reminder-body = This is a reminder of your upcoming audition date. We are looking forward to seeing you!
+
+    Day: { $day }
+    Time: { $from } - { $to }
+    Room: { $room }
+
+    If you cannot come, we urgently ask you to cancel your date. To do so, please follow this link:
+mail-booking-cancelled-subject = Audition date booking cancelled
+mail-booking-cancelled-body = Your booking of the following audition date has been cancelled:
+
+    Day: { $day }
+    Time: { $from } - { $to }
+    Room: { $room }
 mail-date-cancel-subject = Audition date cancelled
+calendar-summary = Audition date: { $datetype }
+calendar-feed-name = Audition dates
+calendar-feed-name-date-type = Audition dates: { $datetype }
+calendar-feed-booked = { $datetype }: { $name } ({ $voice })
+calendar-feed-free = { $datetype }: not booked
 mail-waiting-list-confirmation = You have been added to our waiting list for new audition dates successfully.
 mail-waiting-list-invite = There are new audition dates available. Please visit our website to book one:
 mail-waiting-list-footer = Please follow the below link to leave the waiting list:
+mail-unsubscribe = Leave the waiting list
+mail-result-subject = Your audition result for { $datetype }
+mail-result-accepted-body = Dear { $name },
+
+    thank you for your audition. We are happy to inform you that you have been accepted into the { $datetype }!
+mail-result-voice = You will sing / play in the following voice: { $voice }
+mail-result-rejected-body = Dear { $name },
+
+    thank you for your audition. Unfortunately, we cannot offer you a place in the { $datetype } this time.
+mail-result-waitlisted-body = Dear { $name },
+
+    thank you for your audition. We have put you on our waiting list for the { $datetype } and will contact you as soon as a place becomes available.
@@ -0,0 +1,9 @@
+create type result_outcome as enum ('accepted', 'rejected', 'waitlisted');
+
+create table booking_results (
+    booking text primary key references bookings (token) on delete cascade,
+    outcome result_outcome not null,
+    voice integer references voices (id) on delete set null,
+    notes text not null default '',
+    updated timestamp with time zone not null default now()
+);
@@ -0,0 +1 @@
+alter table booking_results add column mailed timestamp with time zone;
@@ -0,0 +1,16 @@
+create type admin_role as enum ('owner', 'organizer', 'jury', 'viewer');
+
+-- existing admins could do everything so far
+alter table admins add column role admin_role not null default 'viewer';
+update admins set role = 'owner';
+
+alter table invites
+add column role admin_role not null default 'viewer',
+add column date_types text[] not null default '{}';
+
+-- admins without an entry here may access all date types
+create table admins_date_types (
+    admin_id text not null references admins (id) on delete cascade,
+    date_type text not null references date_types (id) on delete cascade,
+    primary key (admin_id, date_type)
+);
@@ -0,0 +1,8 @@
+create table sessions (
+    id text primary key default gen_random_uuid(),
+    admin_id text not null references admins (id) on delete cascade,
+    created timestamp with time zone not null default now(),
+    last_seen timestamp with time zone not null default now(),
+    valid_until timestamp with time zone not null,
+    user_agent text not null default ''
+);
@@ -0,0 +1,15 @@
+alter table admins add column totp_secret text;
+alter table admins add column totp_enabled boolean not null default false;
+alter table admins add column totp_last_step bigint;
+
+create table recovery_codes (
+    id serial primary key,
+    admin_id text not null references admins (id) on delete cascade,
+    code text not null
+);
+
+create table settings (
+    id boolean primary key default true check (id),
+    require_two_factor boolean not null default false
+);
+insert into settings default values;
@@ -0,0 +1,21 @@
+create table rate_limit_attempts (
+    action text not null,
+    key text not null,
+    created timestamp with time zone not null default now()
+);
+create index rate_limit_attempts_lookup on rate_limit_attempts (action, key, created);
+
+create table rate_limit_lockouts (
+    action text not null,
+    key text not null,
+    until timestamp with time zone not null,
+    primary key (action, key)
+);
+
+create table audit_log (
+    id bigserial primary key,
+    created timestamp with time zone not null default now(),
+    admin_id text references admins (id) on delete set null,
+    action text not null,
+    details text not null default ''
+);
@@ -0,0 +1,6 @@
+alter table audit_log add column targets text[] not null default '{}';
+alter table audit_log add column payload jsonb not null default '{}';
+update audit_log set payload = jsonb_build_object('details', details) where details <> '';
+alter table audit_log drop column details;
+
+create index audit_log_created on audit_log (created desc);
@@ -0,0 +1,13 @@
+-- date types and voices are ordered explicitly instead of by insertion
+alter table date_types add column sort_order integer not null default 0;
+alter table voices add column sort_order integer not null default 0;
+update voices set sort_order = id;
+
+-- voices are removed together with their date type
+alter table voices drop constraint voices_date_type_fkey;
+alter table voices
+add constraint voices_date_type_fkey foreign key (date_type) references date_types (id) on delete cascade;
+
+-- announcements are created for every date type, so the positions can no longer be a fixed enum
+alter table announcements alter column position type text using position::text;
+drop type announcement_position;
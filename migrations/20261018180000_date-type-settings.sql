@@ -0,0 +1,11 @@
+alter table date_types
+add column enabled boolean not null default false,
+add column opens_at timestamptz,
+add column application_deadline timestamptz,
+add column days_deadline integer not null default 1 check (days_deadline >= 0),
+add column dates_per_day integer not null default 3 check (dates_per_day >= 0),
+add constraint date_types_opens_before_deadline
+    check (opens_at is null or application_deadline is null or opens_at < application_deadline);
+
+-- these were the defaults of enabled_date_types, days_deadline and dates_per_day in Rocket.toml
+update date_types set enabled = true where id = 'choir';
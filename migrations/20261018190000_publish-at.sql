@@ -0,0 +1,6 @@
+-- dates are only offered from this point in time on
+alter table dates add column publish_at timestamptz not null default now();
+
+-- whether the waiting list has been told about the date, which happens once it is published
+alter table dates add column waiting_list_notified boolean not null default true;
+alter table dates alter column waiting_list_notified set default false;
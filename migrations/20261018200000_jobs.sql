@@ -0,0 +1,18 @@
+create table jobs (
+    id bigserial primary key,
+    kind text not null,
+    run_at timestamptz not null default now(),
+    -- recurring jobs are rescheduled after every run, one-off jobs are deleted
+    interval_seconds integer check (interval_seconds > 0),
+    -- set while a worker runs the job so that other replicas skip it
+    locked_until timestamptz,
+    attempts integer not null default 0,
+    last_error text
+);
+
+create unique index jobs_recurring on jobs (kind) where interval_seconds is not null;
+create index jobs_run_at on jobs (run_at);
+
+-- the waiting lists are notified by a job at the time dates are published
+insert into jobs (kind, run_at)
+select distinct 'publish-dates', publish_at from dates where not waiting_list_notified;
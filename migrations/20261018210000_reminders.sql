@@ -0,0 +1,13 @@
+-- existing bookings only get the reminders which are still ahead
+alter table bookings add column created timestamptz not null default now();
+
+-- hours before a date at which the candidate is reminded of it
+alter table date_types
+add column reminder_hours integer[] not null default '{48, 2}' check (0 < all (reminder_hours));
+
+create table booking_reminders (
+    booking text not null references bookings (token) on delete cascade,
+    hours integer not null,
+    sent timestamptz not null default now(),
+    primary key (booking, hours)
+);
@@ -0,0 +1,14 @@
+-- mails are rendered when the triggering change is made and delivered by a background job
+create table mail_outbox (
+    id bigserial primary key,
+    recipient text not null,
+    subject text not null,
+    body text not null,
+    created timestamptz not null default now(),
+    -- null once delivery has been given up
+    next_attempt timestamptz default now(),
+    attempts integer not null default 0,
+    last_error text
+);
+
+create index mail_outbox_next_attempt on mail_outbox (next_attempt);
@@ -0,0 +1,2 @@
+-- mails queued before this migration only have a plain text body
+alter table mail_outbox add column html text;
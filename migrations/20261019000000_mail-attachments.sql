@@ -0,0 +1,9 @@
+create table mail_attachments (
+    id bigserial primary key,
+    mail bigint not null references mail_outbox (id) on delete cascade,
+    filename text not null,
+    content_type text not null,
+    content bytea not null
+);
+
+create index mail_attachments_mail on mail_attachments (mail);
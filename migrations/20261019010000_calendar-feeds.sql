@@ -0,0 +1,2 @@
+-- secret token of the admin's calendar subscription or null if it is disabled
+alter table admins add column calendar_token text unique;
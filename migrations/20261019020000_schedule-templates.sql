@@ -0,0 +1,14 @@
+create table schedule_templates (
+    id serial primary key,
+    name text not null unique,
+    slot_minutes integer not null check (slot_minutes > 0),
+    buffer_minutes integer not null default 0 check (buffer_minutes >= 0)
+);
+
+-- the times of a day in which dates are offered, the gaps between them are breaks
+create table schedule_template_blocks (
+    template integer not null references schedule_templates (id) on delete cascade,
+    start_time time not null,
+    end_time time not null check (start_time < end_time),
+    primary key (template, start_time)
+);
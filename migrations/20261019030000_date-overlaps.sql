@@ -0,0 +1,21 @@
+-- a room can only be used for one date at a time
+create extension if not exists btree_gist;
+
+-- dates created before these checks may end before they start or overlap other dates in their room,
+-- they are kept and marked, so the checks only apply to dates created from now on
+alter table dates add column legacy_conflict boolean not null default false;
+
+update dates set legacy_conflict = true
+where from_date >= to_date or exists (
+    select from dates other
+    where other.id <> dates.id
+    and other.room_id = dates.room_id
+    and other.from_date < other.to_date
+    and dates.from_date < dates.to_date
+    and tstzrange(other.from_date, other.to_date) && tstzrange(dates.from_date, dates.to_date)
+);
+
+alter table dates
+add constraint dates_time_order check (from_date < to_date or legacy_conflict),
+add constraint dates_room_overlap exclude using gist (room_id with =, tstzrange(from_date, to_date) with &&)
+where (not legacy_conflict);
@@ -0,0 +1,4 @@
+-- deleting a date type must not silently lift the restriction of admins scoped to it
+alter table admins_date_types
+drop constraint admins_date_types_date_type_fkey,
+add constraint admins_date_types_date_type_fkey foreign key (date_type) references date_types (id);
@@ -1,35 +1,67 @@
 use std::collections::BTreeMap;
-use std::collections::HashSet;
+use std::collections::HashMap;
 
 use anyhow::anyhow;
 use chrono::DateTime;
 use chrono::Duration;
 use chrono::Local;
+use chrono::NaiveDate;
 use chrono::NaiveDateTime;
-use rocket::form::Form;
+use chrono::Utc;
+use fluent_templates::fluent_bundle::FluentValue;
+use itertools::Itertools;
+use lazy_regex::regex_is_match;
+use lettre::message::Mailbox;
+use map_macro::hash_map;
 use rocket::form::FromForm;
+use rocket::fs::TempFile;
+use rocket::http::ContentType;
+use rocket::http::Header;
+use rocket::http::Status;
 use rocket::response::Redirect;
 use rocket::serde::json::Json;
+use rocket::tokio;
 use rocket::State;
 use rocket_db_pools::Connection;
 use rocket_dyn_templates::{context, Template};
 use serde::Deserialize;
 use serde::Serialize;
+use serde_json::json;
+use sqlx::Acquire;
+use sqlx::PgConnection;
 
+use crate::audit;
+use crate::auth::require::{
+    ManageAnnouncements, ManageDateTypes, ManageDates, ManageMails, ManageRooms, RecordResults,
+    SendResultMails, ViewAuditLog,
+};
+use crate::auth::Authorized;
+use crate::csrf::{CsrfForm, CsrfToken};
+use crate::ics;
+use crate::jobs;
+use crate::language::{LOCALES, SUPPORTED_LANGUAGES};
+use crate::mail::notify_published_dates;
 use crate::mail::send_mail;
-use crate::mail::waiting_list_notify;
+use crate::mail::send_mail_with_attachments;
+use crate::mail::CalendarBooking;
 use crate::mail::MailBody;
+use crate::model::days_between;
+use crate::model::generate_slots;
 use crate::model::validate_room;
 use crate::model::DateType;
+use crate::model::DateTypeSettings;
+use crate::model::FormDate;
 use crate::model::FormDateTime;
 use crate::model::IntoInner;
 use crate::model::Message;
 use crate::model::MessageType;
 use crate::model::Room;
+use crate::model::ScheduleTemplate;
+use crate::model::TimeBlocks;
 use crate::model::Voice;
+use crate::model::VoicePosition;
 use crate::util::datetime_to_day;
 use crate::Config;
-use crate::Mailer;
 use crate::{auth::Admin, language::Language, Database, RocketResult};
 
 #[derive(Serialize, Deserialize)]
@@ -48,16 +80,29 @@ pub struct BookableDate {
     pub room_number: String,
     pub booking: Option<Booking>,
     pub date_type: DateType,
+    /// When the date will be published or `None` if it already is.
+    pub publish_at: Option<DateTime<Local>>,
 }
 
 #[derive(Serialize)]
 pub struct Booking {
+    token: String,
     email: String,
     person_name: String,
     notes: String,
     voice: Voice,
+    result: Option<BookingResult>,
 }
 
+#[derive(Serialize)]
+pub struct BookingResult {
+    outcome: String,
+    voice: Option<Voice>,
+    notes: String,
+}
+
+pub const RESULT_OUTCOMES: [&str; 3] = ["accepted", "rejected", "waitlisted"];
+
 #[get("/admin/dashboard?<day>")]
 pub async fn dashboard(
     lang: Language,
@@ -79,7 +124,9 @@ pub async fn dashboard(
             .unwrap(),
         None => {
             let now = match sqlx::query_scalar!(
-                "select min(from_date) from dates where from_date >= now()"
+                "select min(from_date) from dates \
+                where from_date >= now() and ($1::text[] is null or date_type = any($1))",
+                admin.date_types.as_deref(),
             )
             .fetch_one(&mut *db)
             .await?
@@ -91,7 +138,10 @@ pub async fn dashboard(
         }
     });
     let available_days: Vec<DateTime<Local>> = sqlx::query!(
-        r#"select distinct date_trunc('day', from_date) as "day!" from dates order by "day!" asc"#
+        r#"select distinct date_trunc('day', from_date) as "day!" from dates
+        where $1::text[] is null or date_type = any($1)
+        order by "day!" asc"#,
+        admin.date_types.as_deref(),
     )
     .fetch_all(&mut *db)
     .await?
@@ -106,23 +156,35 @@ pub async fn dashboard(
         room_number,
         dates.date_type,
         date_types_translations.display_name as date_type_display_name,
+        token as "token?",
         email as "email?",
         person_name as "person_name?",
-        notes as "notes?",
+        bookings.notes as "notes?",
         voices.value as "voice?",
-        voices_translations.display_name as "voice_display_name?"
+        voices_translations.display_name as "voice_display_name?",
+        booking_results.outcome::text as "outcome?",
+        booking_results.notes as "result_notes?",
+        result_voices.value as "result_voice?",
+        result_voices_translations.display_name as "result_voice_display_name?",
+        case when publish_at > now() then publish_at end as publish_at
         from dates
         join date_types_translations on date_types_translations.date_type = dates.date_type
         join rooms on dates.room_id = rooms.id
         left join bookings on bookings.date_id = dates.id
         left join voices on bookings.voice = voices.id
         left join voices_translations on voices.id = voices_translations.voice
+        left join booking_results on booking_results.booking = bookings.token
+        left join voices result_voices on booking_results.voice = result_voices.id
+        left join voices_translations result_voices_translations
+            on result_voices.id = result_voices_translations.voice and result_voices_translations.lang = $2
         where $1 <= from_date and from_date <= $1 + interval '1 day'
         and date_types_translations.lang = $2
         and (voices_translations.lang is null or voices_translations.lang = $2)
+        and ($3::text[] is null or dates.date_type = any($3))
         order by from_date asc, date_type asc, room_number asc"#,
         &day,
-        &lang
+        &lang,
+        admin.date_types.as_deref(),
     )
     .fetch_all(&mut *db)
     .await?
@@ -131,6 +193,7 @@ pub async fn dashboard(
         let booking = match record.email.is_some() {
             false => None,
             true => Some(Booking {
+                token: record.token.unwrap(),
                 email: record.email.unwrap(),
                 person_name: record.person_name.unwrap(),
                 notes: record.notes.unwrap(),
@@ -141,6 +204,14 @@ pub async fn dashboard(
                     },
                     _ => panic!("Booking without a voice or a voice without a translation!"),
                 },
+                result: record.outcome.map(|outcome| BookingResult {
+                    outcome,
+                    voice: record.result_voice.map(|voice| Voice {
+                        value: voice,
+                        display_name: record.result_voice_display_name,
+                    }),
+                    notes: record.result_notes.unwrap_or_default(),
+                }),
             }),
         };
         BookableDate {
@@ -153,18 +224,351 @@ pub async fn dashboard(
                 value: record.date_type,
                 display_name: Some(record.date_type_display_name),
             },
+            publish_at: record
+                .publish_at
+                .map(|publish_at| publish_at.with_timezone(&Local)),
         }
     })
     .collect();
     Ok(Template::render(
         "dashboard",
-        context! { lang, display_name, dates, available_days, day },
+        context! {
+            lang,
+            display_name,
+            dates,
+            available_days,
+            day,
+            permissions: admin.permissions(),
+        },
+    ))
+}
+
+#[get("/admin/export")]
+pub async fn export_get(
+    lang: Language,
+    admin: Admin,
+    mut db: Connection<Database>,
+) -> RocketResult<Template> {
+    let lang = lang.into_string();
+    let date_types = admin.accessible_date_types(&mut db, &lang).await?;
+    Ok(Template::render(
+        "export",
+        context! {
+            lang,
+            date_types,
+        },
     ))
 }
 
+#[derive(Responder)]
+#[response(content_type = "text/csv; charset=utf-8")]
+pub struct CsvFile {
+    content: String,
+    disposition: Header<'static>,
+}
+
+/// Exports dates and their bookings as CSV. Empty parameters don't filter, `from` and `to` are
+/// days and both included.
+#[get("/admin/export/dates.csv?<date_type>&<from>&<to>&<status>&<separator>")]
+#[allow(clippy::too_many_arguments)]
+pub async fn export_csv_get(
+    lang: Language,
+    admin: Admin,
+    mut db: Connection<Database>,
+    date_type: Option<&str>,
+    from: Option<&str>,
+    to: Option<&str>,
+    status: Option<&str>,
+    separator: Option<&str>,
+) -> RocketResult<Result<CsvFile, Status>> {
+    let lang = lang.into_string();
+    let date_type = date_type.filter(|date_type| !date_type.is_empty());
+    if date_type.is_some_and(|date_type| !admin.may_access(date_type)) {
+        return Ok(Err(Status::Forbidden));
+    }
+    // An empty day doesn't limit the export, an invalid one is rejected.
+    let parse_day = |day: Option<&str>| -> Option<Option<DateTime<Local>>> {
+        match day.filter(|day| !day.is_empty()) {
+            Some(day) => NaiveDate::parse_from_str(day, "%Y-%m-%d")
+                .ok()?
+                .and_hms_opt(0, 0, 0)?
+                .and_local_timezone(Local)
+                .earliest()
+                .map(|day| Some(datetime_to_day(day))),
+            None => Some(None),
+        }
+    };
+    let (from, to) = match (parse_day(from), parse_day(to)) {
+        (Some(from), Some(to)) => (from, to.map(|to| to + Duration::days(1))),
+        _ => return Ok(Err(Status::BadRequest)),
+    };
+    let booked = match status.unwrap_or_default() {
+        "booked" => Some(true),
+        "free" => Some(false),
+        "" | "all" => None,
+        _ => return Ok(Err(Status::BadRequest)),
+    };
+    let separator = match separator.unwrap_or_default() {
+        "semicolon" => ';',
+        _ => ',',
+    };
+    let dates = sqlx::query!(
+        r#"select
+        from_date,
+        to_date,
+        room_number,
+        date_types_translations.display_name as date_type_display_name,
+        person_name as "person_name?",
+        email as "email?",
+        voices_translations.display_name as "voice_display_name?",
+        bookings.notes as "notes?",
+        bookings.lang as "booking_lang?"
+        from dates
+        join rooms on dates.room_id = rooms.id
+        join date_types_translations
+            on date_types_translations.date_type = dates.date_type and date_types_translations.lang = $1
+        left join bookings on bookings.date_id = dates.id
+        left join voices_translations
+            on voices_translations.voice = bookings.voice and voices_translations.lang = $1
+        where ($2::text[] is null or dates.date_type = any($2))
+        and ($3::text is null or dates.date_type = $3)
+        and ($4::timestamptz is null or from_date >= $4)
+        and ($5::timestamptz is null or from_date < $5)
+        and ($6::boolean is null or (bookings.token is not null) = $6)
+        order by from_date asc, date_types_translations.display_name asc, room_number asc"#,
+        &lang,
+        admin.date_types.as_deref(),
+        date_type,
+        from,
+        to,
+        booked,
+    )
+    .fetch_all(&mut *db)
+    .await?;
+    let language_id = lang.parse()?;
+    let translate = |key: &str| -> anyhow::Result<String> {
+        LOCALES
+            .lookup_single_language::<&str>(&language_id, key, None)
+            .ok_or_else(|| anyhow!("Missing translation for {}!", key))
+    };
+    // The byte order mark makes spreadsheet applications detect the encoding.
+    let mut content = String::from('\u{feff}');
+    let header = [
+        "date-type",
+        "day",
+        "from-date",
+        "to-date",
+        "room",
+        "export-status",
+        "person-name",
+        "email",
+        "voice",
+        "notes",
+        "export-language",
+    ]
+    .into_iter()
+    .map(translate)
+    .collect::<anyhow::Result<Vec<_>>>()?;
+    crate::csv::write_record(&mut content, separator, &header);
+    let (booked_text, free_text) = (translate("export-booked")?, translate("export-free")?);
+    for date in &dates {
+        let from_date = date.from_date.with_timezone(&Local);
+        let to_date = date.to_date.with_timezone(&Local);
+        let booking_language = match &date.booking_lang {
+            Some(booking_lang) => translate(&format!("language-{}", booking_lang))?,
+            None => String::new(),
+        };
+        crate::csv::write_record(
+            &mut content,
+            separator,
+            &[
+                date.date_type_display_name.as_str(),
+                &from_date.format("%Y-%m-%d").to_string(),
+                &from_date.format("%H:%M").to_string(),
+                &to_date.format("%H:%M").to_string(),
+                &date.room_number,
+                if date.email.is_some() {
+                    &booked_text
+                } else {
+                    &free_text
+                },
+                date.person_name.as_deref().unwrap_or_default(),
+                date.email.as_deref().unwrap_or_default(),
+                date.voice_display_name.as_deref().unwrap_or_default(),
+                date.notes.as_deref().unwrap_or_default(),
+                &booking_language,
+            ],
+        );
+    }
+    // Exports contain personal data of the candidates, so they are logged.
+    audit::log(
+        &mut db,
+        Some(&admin.id),
+        "bookings-export",
+        &[],
+        json!({
+            "date_type": date_type,
+            "from": from,
+            "to": to,
+            "booked": booked,
+            "rows": dates.len(),
+        }),
+    )
+    .await?;
+    Ok(Ok(CsvFile {
+        content,
+        disposition: Header::new(
+            "Content-Disposition",
+            "attachment; filename=\"audition-dates.csv\"",
+        ),
+    }))
+}
+
+/// The calendar subscription of an admin with all dates they have access to, or only the dates of
+/// one date type.
+#[get("/calendar/<token>/dates.ics?<date_type>&<lang>")]
+pub async fn calendar_feed(
+    mut db: Connection<Database>,
+    config: &State<Config>,
+    token: &str,
+    date_type: Option<&str>,
+    lang: Option<&str>,
+) -> RocketResult<Option<(ContentType, String)>> {
+    let lang = lang
+        .filter(|lang| SUPPORTED_LANGUAGES.contains(lang))
+        .unwrap_or("de");
+    // The token stands in for a login, so it only works once two-factor authentication is set up
+    // where it is required.
+    let admin_id = match sqlx::query_scalar!(
+        "select admins.id from admins cross join settings \
+        where calendar_token = $1 and (totp_enabled or not require_two_factor)",
+        token
+    )
+    .fetch_optional(&mut *db)
+    .await?
+    {
+        Some(admin_id) => admin_id,
+        None => return Ok(None),
+    };
+    let name_key = match date_type {
+        Some(_) => "calendar-feed-name-date-type",
+        None => "calendar-feed-name",
+    };
+    let date_type_display_name = match date_type {
+        Some(date_type) => match sqlx::query_scalar!(
+            "select display_name from date_types_translations \
+            where date_type = $1 and lang = $2",
+            date_type,
+            lang
+        )
+        .fetch_optional(&mut *db)
+        .await?
+        {
+            Some(display_name) => display_name,
+            None => return Ok(None),
+        },
+        None => String::new(),
+    };
+    let translate = |key: &str, args: &HashMap<&str, FluentValue>| -> anyhow::Result<String> {
+        Ok(LOCALES
+            .lookup_single_language(&lang.parse()?, key, Some(args))
+            .ok_or_else(|| anyhow!("Missing translation for {}!", key))?
+            // Calendars show plain text, so Fluent's bidi isolation marks are removed.
+            .replace(['\u{2068}', '\u{2069}'], ""))
+    };
+    let name = translate(
+        name_key,
+        &hash_map! { "datetype" => date_type_display_name.into() },
+    )?;
+    let dates = sqlx::query!(
+        r#"select
+        dates.id,
+        from_date,
+        to_date,
+        room_number,
+        date_types_translations.display_name as date_type_display_name,
+        email as "email?",
+        person_name as "person_name?",
+        bookings.notes as "notes?",
+        voices_translations.display_name as "voice_display_name?"
+        from dates
+        join rooms on dates.room_id = rooms.id
+        join date_types_translations
+            on date_types_translations.date_type = dates.date_type and date_types_translations.lang = $2
+        left join bookings on bookings.date_id = dates.id
+        left join voices_translations
+            on voices_translations.voice = bookings.voice and voices_translations.lang = $2
+        where ($3::text is null or dates.date_type = $3)
+        and (not exists (select from admins_date_types where admin_id = $1)
+            or dates.date_type in (select date_type from admins_date_types where admin_id = $1))
+        order by from_date asc, room_number asc"#,
+        &admin_id,
+        lang,
+        date_type,
+    )
+    .fetch_all(&mut *db)
+    .await?;
+    let organizer: Mailbox = config.email_from_address.parse()?;
+    let mut summaries = Vec::with_capacity(dates.len());
+    let mut descriptions = Vec::with_capacity(dates.len());
+    for date in &dates {
+        let mut args: HashMap<&str, FluentValue> =
+            hash_map! { "datetype" => date.date_type_display_name.as_str().into() };
+        let key = match &date.person_name {
+            Some(person_name) => {
+                args.insert("name", person_name.as_str().into());
+                args.insert(
+                    "voice",
+                    date.voice_display_name
+                        .as_deref()
+                        .unwrap_or_default()
+                        .into(),
+                );
+                "calendar-feed-booked"
+            }
+            None => "calendar-feed-free",
+        };
+        summaries.push(translate(key, &args)?);
+        descriptions.push(match (&date.email, &date.notes) {
+            (Some(email), Some(notes)) if !notes.is_empty() => format!("{}\n\n{}", email, notes),
+            (Some(email), _) => email.clone(),
+            _ => String::new(),
+        });
+    }
+    let events: Vec<ics::Event> = dates
+        .iter()
+        .zip(&summaries)
+        .zip(&descriptions)
+        .map(|((date, summary), description)| ics::Event {
+            // The id stays the same when the date is booked, so calendars update the event.
+            uid: ics::uid(&format!("date-{}", date.id), &config.web_address),
+            start: date.from_date.with_timezone(&Local),
+            end: date.to_date.with_timezone(&Local),
+            summary,
+            location: &date.room_number,
+            description,
+            organizer: organizer.email.as_ref(),
+            attendee: date.email.as_deref().unwrap_or_default(),
+            tentative: date.email.is_none(),
+        })
+        .collect();
+    Ok(Some((
+        ContentType::Calendar,
+        ics::render_feed(&name, &events),
+    )))
+}
+
 #[get("/admin/date-cancel?<dates>")]
-pub async fn date_cancel_get(lang: Language, _admin: Admin, dates: Vec<i32>) -> Template {
-    Template::render("date-cancel", context! { lang: lang.into_string(), dates })
+pub async fn date_cancel_get(
+    lang: Language,
+    csrf: CsrfToken,
+    _admin: Authorized<ManageDates>,
+    dates: Vec<i32>,
+) -> Template {
+    Template::render(
+        "date-cancel",
+        context! { csrf_token: csrf.as_str(), lang: lang.into_string(), dates },
+    )
 }
 
 #[derive(FromForm)]
@@ -175,121 +579,413 @@ pub struct DateCancelForm<'r> {
 
 #[post("/admin/date-cancel", data = "<form>")]
 pub async fn date_cancel_post(
-    _admin: Admin,
+    admin: Authorized<ManageDates>,
     mut db: Connection<Database>,
     config: &State<Config>,
-    mailer: &State<Mailer>,
-    form: Form<DateCancelForm<'_>>,
-) -> RocketResult<Redirect> {
+    form: CsrfForm<DateCancelForm<'_>>,
+) -> RocketResult<Result<Redirect, Status>> {
     let DateCancelForm {
         dates,
         explanations,
     } = form.into_inner();
-    let mut emails = Vec::new();
-    for date in &dates {
-        emails.extend(
-            sqlx::query!("select email, lang from bookings where date_id = $1", &date)
-                .fetch_all(&mut *db)
-                .await?
-                .into_iter()
-                .map(|record| (record.email, record.lang)),
-        );
+    let date_types = sqlx::query_scalar!("select date_type from dates where id = any($1)", &dates)
+        .fetch_all(&mut *db)
+        .await?;
+    if !date_types
+        .iter()
+        .all(|date_type| admin.may_access(date_type))
+    {
+        return Ok(Err(Status::Forbidden));
     }
-    for (email, lang) in emails {
-        let explanation = explanations[&lang];
+    let bookings = sqlx::query!(
+        r#"select
+        bookings.token,
+        bookings.email,
+        bookings.lang,
+        dates.from_date,
+        dates.to_date,
+        rooms.room_number,
+        date_types_translations.display_name
+        from bookings
+        join dates on dates.id = bookings.date_id
+        join rooms on rooms.id = dates.room_id
+        join date_types_translations
+            on date_types_translations.date_type = dates.date_type and date_types_translations.lang = bookings.lang
+        where dates.id = any($1)"#,
+        &dates
+    )
+    .fetch_all(&mut *db)
+    .await?;
+    let mut tx = db.begin().await?;
+    let mut notified = Vec::new();
+    for booking in bookings {
+        let explanation = explanations[&booking.lang];
         if !explanation.is_empty() {
-            send_mail(
+            // The cancellation removes the date from the candidate's calendar.
+            let calendar = CalendarBooking {
+                token: &booking.token,
+                from_date: booking.from_date.with_timezone(&Local),
+                to_date: booking.to_date.with_timezone(&Local),
+                room_number: &booking.room_number,
+                date_type: &booking.display_name,
+                description: explanation,
+            }
+            .attachment(config, &booking.email, &booking.lang, ics::Method::Cancel)?;
+            send_mail_with_attachments(
+                &mut tx,
                 config,
-                mailer,
-                &email,
-                &lang,
+                &booking.email,
+                &booking.lang,
                 "mail-date-cancel-subject",
                 None,
                 MailBody::Raw(String::from(explanation)),
+                vec![calendar],
             )
             .await?;
+            notified.push(booking.email);
         }
     }
+    let mut deleted = Vec::new();
     for date in &dates {
-        sqlx::query!("delete from dates where id = $1", &date)
-            .execute(&mut *db)
-            .await?;
+        if let Some(record) = sqlx::query!(
+            "delete from dates where id = $1 returning from_date, to_date, date_type, room_id",
+            &date
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        {
+            deleted.push(json!({
+                "from_date": record.from_date,
+                "to_date": record.to_date,
+                "date_type": record.date_type,
+                "room_id": record.room_id,
+            }));
+        }
     }
-    Ok(Redirect::to(uri!(dashboard(day = Option::<&str>::None))))
+    audit::log(
+        &mut tx,
+        Some(&admin.id),
+        "date-cancel",
+        &dates.iter().map(i32::to_string).collect::<Vec<_>>(),
+        json!({ "dates": deleted, "explanations": explanations, "notified": notified }),
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(Ok(Redirect::to(uri!(dashboard(
+        day = Option::<&str>::None
+    )))))
 }
 
-#[get("/admin/date-new-1")]
-pub async fn date_new_1_get(
-    lang: Language,
-    _admin: Admin,
-    mut db: Connection<Database>,
-) -> RocketResult<Template> {
-    let lang = lang.into_string();
+/// What a new date overlaps with in its room.
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Conflict {
+    Existing {
+        from_date: DateTime<Local>,
+        to_date: DateTime<Local>,
+        date_type: String,
+        booked: bool,
+    },
+    /// Another new date which comes before in the list.
+    New,
+}
+
+/// Finds the conflict of every new date with the existing dates and the new dates before it, as a
+/// room can only be used for one date at a time. Dates with a conflict don't count as conflicts
+/// of the following dates.
+async fn find_conflicts(
+    db: &mut PgConnection,
+    dates: &[Date],
+    lang: &str,
+) -> anyhow::Result<Vec<Option<Conflict>>> {
+    let existing = match (
+        dates.iter().map(|date| date.from_date).min(),
+        dates.iter().map(|date| date.to_date).max(),
+    ) {
+        (Some(from_date), Some(to_date)) => {
+            sqlx::query!(
+                r#"select
+                room_id as "room_id!",
+                from_date,
+                to_date,
+                display_name,
+                exists (select from bookings where bookings.date_id = dates.id) as "booked!"
+                from dates
+                join date_types_translations
+                    on date_types_translations.date_type = dates.date_type and lang = $3
+                where room_id = any($4) and from_date < $2 and $1 < to_date
+                order by from_date"#,
+                from_date,
+                to_date,
+                lang,
+                &dates.iter().map(|date| date.room_id).collect::<Vec<_>>(),
+            )
+            .fetch_all(&mut *db)
+            .await?
+        }
+        _ => Vec::new(),
+    };
+    let mut accepted: Vec<&Date> = Vec::new();
+    let mut conflicts = Vec::with_capacity(dates.len());
+    for date in dates {
+        let overlaps = |room_id: i32, from_date: DateTime<Utc>, to_date: DateTime<Utc>| {
+            room_id == date.room_id && from_date < date.to_date && date.from_date < to_date
+        };
+        let conflict = if let Some(existing) = existing
+            .iter()
+            .find(|existing| overlaps(existing.room_id, existing.from_date, existing.to_date))
+        {
+            Some(Conflict::Existing {
+                from_date: existing.from_date.with_timezone(&Local),
+                to_date: existing.to_date.with_timezone(&Local),
+                date_type: existing.display_name.clone(),
+                booked: existing.booked,
+            })
+        } else if accepted.iter().any(|other| {
+            overlaps(
+                other.room_id,
+                other.from_date.with_timezone(&Utc),
+                other.to_date.with_timezone(&Utc),
+            )
+        }) {
+            Some(Conflict::New)
+        } else {
+            accepted.push(date);
+            None
+        };
+        conflicts.push(conflict);
+    }
+    Ok(conflicts)
+}
+
+#[derive(Serialize)]
+struct DatePreview<'a> {
+    #[serde(flatten)]
+    date: &'a Date,
+    conflict: Option<Conflict>,
+}
+
+/// Shows new dates for confirmation, where dates with a conflict aren't selected.
+fn render_date_new_2(
+    lang: String,
+    csrf: CsrfToken,
+    dates: &[Date],
+    conflicts: Vec<Option<Conflict>>,
+    publish_at: Option<DateTime<Local>>,
+    messages: Vec<Message>,
+) -> Template {
+    // The template groups the dates by day and selects them by their position, so they have to be
+    // in chronological order.
+    let previews: Vec<DatePreview> = dates
+        .iter()
+        .zip(conflicts)
+        .map(|(date, conflict)| DatePreview { date, conflict })
+        .sorted_by_key(|preview| preview.date.from_date)
+        .collect();
+    let dates: Vec<&Date> = previews.iter().map(|preview| preview.date).collect();
+    Template::render(
+        "date-new-2",
+        context! {
+            csrf_token: csrf.as_str(),
+            lang,
+            dates,
+            previews,
+            publish_at,
+            messages,
+        },
+    )
+}
+
+/// The entries of the date generator, which are shown again if they contain errors.
+#[derive(Serialize)]
+pub struct DateNew1Values<'r> {
+    date_type: &'r str,
+    room: &'r str,
+    from_day: NaiveDate,
+    to_day: NaiveDate,
+    weekdays: Vec<u32>,
+    times: String,
+    slot_minutes: u32,
+    buffer_minutes: u32,
+    publish_at: Option<DateTime<Local>>,
+}
+
+async fn render_date_new_1(
+    lang: String,
+    csrf: CsrfToken,
+    db: &mut Connection<Database>,
+    admin: &Admin,
+    values: DateNew1Values<'_>,
+    messages: Vec<Message>,
+) -> anyhow::Result<Template> {
     let rooms: Vec<String> = sqlx::query!("select room_number from rooms order by room_number asc")
-        .fetch_all(&mut *db)
+        .fetch_all(&mut **db)
         .await?
         .into_iter()
         .map(|record| record.room_number)
         .collect();
-    let date_types = DateType::get_variants(&mut db, &lang).await?;
+    let date_types = admin.accessible_date_types(db, &lang).await?;
+    let schedule_templates = ScheduleTemplate::get_all(db).await?;
     Ok(Template::render(
         "date-new-1",
         context! {
+            csrf_token: csrf.as_str(),
             lang,
             rooms,
-            room_selected: "",
             date_types,
-            date_type_selected: "",
-            from_date: Local::now(),
-            to_date: Local::now() + Duration::hours(1),
-            interval: 10i32,
+            schedule_templates,
+            values,
+            messages,
         },
     ))
 }
 
+/// Shows the date generator, filled with the times of a schedule template if one is given.
+#[get("/admin/date-new-1?<template>")]
+pub async fn date_new_1_get(
+    lang: Language,
+    csrf: CsrfToken,
+    admin: Authorized<ManageDates>,
+    mut db: Connection<Database>,
+    template: Option<i32>,
+) -> RocketResult<Template> {
+    let template = ScheduleTemplate::get_all(&mut db)
+        .await?
+        .into_iter()
+        .find(|schedule_template| Some(schedule_template.id) == template);
+    let today = Local::now().date_naive();
+    let values = DateNew1Values {
+        date_type: "",
+        room: "",
+        from_day: today,
+        to_day: today,
+        weekdays: (1..=7).collect(),
+        times: template.as_ref().map_or_else(
+            || String::from("10:00-13:00, 14:00-18:00"),
+            |template| template.times.clone(),
+        ),
+        slot_minutes: template
+            .as_ref()
+            .map_or(10, |template| template.slot_minutes as u32),
+        buffer_minutes: template
+            .as_ref()
+            .map_or(0, |template| template.buffer_minutes as u32),
+        publish_at: None,
+    };
+    Ok(render_date_new_1(
+        lang.into_string(),
+        csrf,
+        &mut db,
+        &admin,
+        values,
+        Vec::new(),
+    )
+    .await?)
+}
+
 #[derive(FromForm)]
 pub struct DateNew1Form<'r> {
     date_type: &'r str,
     room: &'r str,
-    from_date: FormDateTime,
-    to_date: FormDateTime,
-    interval: u32,
+    from_day: FormDate,
+    to_day: FormDate,
+    weekdays: Vec<u32>,
+    times: &'r str,
+    slot_minutes: u32,
+    buffer_minutes: u32,
+    publish_at: Option<FormDateTime>,
 }
 
+/// The most days dates can be created for at once.
+const MAX_DAYS: i64 = 366;
+/// The longest time per date and between two dates, a whole day.
+const MAX_SLOT_MINUTES: u32 = 24 * 60;
+
 #[post("/admin/date-new-1", data = "<form>")]
 pub async fn date_new_1_post(
     lang: Language,
-    _admin: Admin,
+    csrf: CsrfToken,
+    admin: Authorized<ManageDates>,
     mut db: Connection<Database>,
-    form: Form<DateNew1Form<'_>>,
+    form: CsrfForm<DateNew1Form<'_>>,
 ) -> RocketResult<Template> {
     let lang = lang.into_string();
     let DateNew1Form {
         date_type,
         room,
-        from_date,
-        to_date,
-        interval,
+        from_day,
+        to_day,
+        weekdays,
+        times,
+        slot_minutes,
+        buffer_minutes,
+        publish_at,
     } = form.into_inner();
+    let publish_at = publish_at.map(IntoInner::into_inner);
+    let from_day = from_day.into_inner();
+    let to_day = to_day.into_inner();
 
     let mut messages = Vec::new();
     let (room, room_id) = validate_room(room, &mut messages, &mut db).await?;
-    let from_date = from_date.into_inner();
-    let to_date = to_date.into_inner();
-    let interval = interval as i64;
-
-    let num_minutes = (to_date - from_date).num_minutes();
-    if from_date >= to_date {
+    let date_types = admin.accessible_date_types(&mut db, &lang).await?;
+    if !date_types.iter().any(|variant| variant.value == date_type) {
+        messages.push(Message {
+            text_key: String::from("validation-select"),
+            message_type: MessageType::Error,
+        });
+    }
+    if from_day > to_day {
         messages.push(Message {
             text_key: String::from("wrong-date-order"),
             message_type: MessageType::Error,
         });
-    } else if num_minutes % interval != 0 {
+    } else if (to_day - from_day).num_days() >= MAX_DAYS {
+        messages.push(Message {
+            text_key: String::from("too-many-dates"),
+            message_type: MessageType::Error,
+        });
+    }
+    if weekdays.is_empty() {
+        messages.push(Message {
+            text_key: String::from("validation-weekdays"),
+            message_type: MessageType::Error,
+        });
+    }
+    let blocks = TimeBlocks::parse(times);
+    if blocks.is_none() {
+        messages.push(Message {
+            text_key: String::from("validation-times"),
+            message_type: MessageType::Error,
+        });
+    }
+    if !(1..=MAX_SLOT_MINUTES).contains(&slot_minutes) {
+        messages.push(Message {
+            text_key: String::from("validation-slot-minutes"),
+            message_type: MessageType::Error,
+        });
+    }
+    if buffer_minutes > MAX_SLOT_MINUTES {
+        messages.push(Message {
+            text_key: String::from("validation-buffer-minutes"),
+            message_type: MessageType::Error,
+        });
+    }
+
+    let slots = match (&blocks, messages.is_empty()) {
+        (Some(blocks), true) => generate_slots(
+            days_between(from_day, to_day, &weekdays),
+            blocks,
+            Duration::minutes(slot_minutes.into()),
+            Duration::minutes(buffer_minutes.into()),
+        ),
+        _ => Vec::new(),
+    };
+    if messages.is_empty() && slots.is_empty() {
         messages.push(Message {
-            text_key: String::from("interval-not-even"),
+            text_key: String::from("no-dates-generated"),
             message_type: MessageType::Error,
         });
-    } else if num_minutes / interval > 1000 {
+    } else if slots.len() > 1000 {
         messages.push(Message {
             text_key: String::from("too-many-dates"),
             message_type: MessageType::Error,
@@ -297,71 +993,245 @@ pub async fn date_new_1_post(
     }
 
     if !messages.is_empty() {
-        let rooms: Vec<String> =
-            sqlx::query!("select room_number from rooms order by room_number asc")
-                .fetch_all(&mut *db)
-                .await?
-                .into_iter()
-                .map(|record| record.room_number)
-                .collect();
-        let date_types = DateType::get_variants(&mut db, &lang).await?;
-        return Ok(Template::render(
-            "date-new-1",
-            context! {
-                lang,
-                rooms,
-                room_selected: room,
-                date_types,
-                date_type_selected: date_type,
-                messages,
-                from_date,
-                to_date,
-                interval,
-            },
-        ));
+        let values = DateNew1Values {
+            date_type,
+            room,
+            from_day,
+            to_day,
+            weekdays,
+            times: blocks.map_or_else(|| String::from(times), |blocks| blocks.to_string()),
+            slot_minutes,
+            buffer_minutes,
+            publish_at,
+        };
+        return Ok(render_date_new_1(lang, csrf, &mut db, &admin, values, messages).await?);
     }
 
     let date_type = DateType::get_by_value(&mut db, date_type, &lang).await?;
-
-    let num_dates = (num_minutes / interval) as i32;
-    let dates: Vec<Date> = (0..num_dates)
+    let dates: Vec<Date> = slots
         .into_iter()
-        .map(|i| Date {
-            from_date: from_date + Duration::minutes(interval) * i,
-            to_date: from_date + Duration::minutes(interval) * (i + 1),
+        .map(|(from_date, to_date)| Date {
+            from_date,
+            to_date,
             room_id,
             date_type: date_type.clone(),
         })
         .collect();
 
+    let conflicts = find_conflicts(&mut db, &dates, &lang).await?;
+    Ok(render_date_new_2(
+        lang,
+        csrf,
+        &dates,
+        conflicts,
+        publish_at,
+        Vec::new(),
+    ))
+}
+
+async fn render_schedule_templates(
+    lang: String,
+    csrf: CsrfToken,
+    db: &mut Connection<Database>,
+    messages: Vec<Message>,
+) -> anyhow::Result<Template> {
+    let schedule_templates = ScheduleTemplate::get_all(db).await?;
     Ok(Template::render(
-        "date-new-2",
+        "schedule-templates",
         context! {
+            csrf_token: csrf.as_str(),
             lang,
-            dates,
-            interval,
+            schedule_templates,
+            messages,
         },
     ))
 }
 
+#[get("/admin/schedule-templates")]
+pub async fn schedule_templates_get(
+    lang: Language,
+    csrf: CsrfToken,
+    _admin: Authorized<ManageDates>,
+    mut db: Connection<Database>,
+) -> RocketResult<Template> {
+    Ok(render_schedule_templates(lang.into_string(), csrf, &mut db, Vec::new()).await?)
+}
+
+#[derive(FromForm)]
+pub struct ScheduleTemplatesForm<'r> {
+    button: &'r str,
+    name: Option<&'r str>,
+    times: Option<&'r str>,
+    slot_minutes: Option<&'r str>,
+    buffer_minutes: Option<&'r str>,
+}
+
+#[post("/admin/schedule-templates", data = "<form>")]
+pub async fn schedule_templates_post(
+    lang: Language,
+    csrf: CsrfToken,
+    admin: Authorized<ManageDates>,
+    mut db: Connection<Database>,
+    form: CsrfForm<ScheduleTemplatesForm<'_>>,
+) -> RocketResult<Result<Template, Status>> {
+    let ScheduleTemplatesForm {
+        button,
+        name,
+        times,
+        slot_minutes,
+        buffer_minutes,
+    } = form.into_inner();
+    let mut messages = Vec::new();
+    if let Some(id) = button.strip_prefix("delete-") {
+        let id: i32 = match id.parse() {
+            Ok(id) => id,
+            Err(_) => return Ok(Err(Status::BadRequest)),
+        };
+        let name = sqlx::query_scalar!(
+            "delete from schedule_templates where id = $1 returning name",
+            id
+        )
+        .fetch_optional(&mut *db)
+        .await?;
+        if let Some(name) = name {
+            audit::log(
+                &mut db,
+                Some(&admin.id),
+                "schedule-template-delete",
+                &[id.to_string()],
+                json!({ "name": name }),
+            )
+            .await?;
+            messages.push(Message {
+                text_key: String::from("schedule-template-deleted"),
+                message_type: MessageType::Success,
+            });
+        }
+    } else if button == "create" {
+        let name = name.unwrap_or_default().trim();
+        if name.is_empty() {
+            messages.push(Message {
+                text_key: String::from("validation-schedule-template-name"),
+                message_type: MessageType::Error,
+            });
+        }
+        let blocks = TimeBlocks::parse(times.unwrap_or_default());
+        if blocks.is_none() {
+            messages.push(Message {
+                text_key: String::from("validation-times"),
+                message_type: MessageType::Error,
+            });
+        }
+        let slot_minutes = slot_minutes
+            .and_then(|minutes| minutes.trim().parse::<u32>().ok())
+            .filter(|minutes| (1..=MAX_SLOT_MINUTES).contains(minutes));
+        if slot_minutes.is_none() {
+            messages.push(Message {
+                text_key: String::from("validation-slot-minutes"),
+                message_type: MessageType::Error,
+            });
+        }
+        // Without a buffer, the dates follow each other directly.
+        let buffer_minutes = match buffer_minutes.map(str::trim).unwrap_or_default() {
+            "" => Some(0),
+            minutes => minutes
+                .parse::<u32>()
+                .ok()
+                .filter(|minutes| *minutes <= MAX_SLOT_MINUTES),
+        };
+        if buffer_minutes.is_none() {
+            messages.push(Message {
+                text_key: String::from("validation-buffer-minutes"),
+                message_type: MessageType::Error,
+            });
+        }
+        if let (Some(blocks), Some(slot_minutes), Some(buffer_minutes), true) =
+            (blocks, slot_minutes, buffer_minutes, messages.is_empty())
+        {
+            let mut tx = db.begin().await?;
+            let id = sqlx::query_scalar!(
+                "insert into schedule_templates (name, slot_minutes, buffer_minutes) \
+                values ($1, $2, $3) on conflict (name) do nothing returning id",
+                name,
+                slot_minutes as i32,
+                buffer_minutes as i32,
+            )
+            .fetch_optional(&mut *tx)
+            .await?;
+            match id {
+                Some(id) => {
+                    for block in &blocks.0 {
+                        sqlx::query!(
+                            "insert into schedule_template_blocks (template, start_time, end_time) \
+                            values ($1, $2, $3)",
+                            id,
+                            block.start,
+                            block.end,
+                        )
+                        .execute(&mut *tx)
+                        .await?;
+                    }
+                    audit::log(
+                        &mut tx,
+                        Some(&admin.id),
+                        "schedule-template-create",
+                        &[id.to_string()],
+                        json!({
+                            "name": name,
+                            "times": blocks.to_string(),
+                            "slot_minutes": slot_minutes,
+                            "buffer_minutes": buffer_minutes,
+                        }),
+                    )
+                    .await?;
+                    messages.push(Message {
+                        text_key: String::from("schedule-template-created"),
+                        message_type: MessageType::Success,
+                    });
+                }
+                None => messages.push(Message {
+                    text_key: String::from("schedule-template-name-taken"),
+                    message_type: MessageType::Error,
+                }),
+            }
+            tx.commit().await?;
+        }
+    } else {
+        return Ok(Err(Status::BadRequest));
+    }
+    Ok(Ok(render_schedule_templates(
+        lang.into_string(),
+        csrf,
+        &mut db,
+        messages,
+    )
+    .await?))
+}
+
 #[derive(FromForm)]
 pub struct DateNew2Form {
     date_selected: Vec<bool>,
     dates: Json<Vec<Date>>,
+    publish_at: Option<FormDateTime>,
 }
 
+/// Creates the selected dates after checking them again, as they are sent back by the browser.
 #[post("/admin/date-new-2", data = "<form>")]
 pub async fn date_new_2_post(
-    _admin: Admin,
+    lang: Language,
+    csrf: CsrfToken,
+    admin: Authorized<ManageDates>,
     mut db: Connection<Database>,
     config: &State<Config>,
-    mailer: &State<Mailer>,
-    form: Form<DateNew2Form>,
-) -> RocketResult<Redirect> {
+    form: CsrfForm<DateNew2Form>,
+) -> RocketResult<Result<Redirect, Result<Template, Status>>> {
+    let lang = lang.into_string();
     let DateNew2Form {
         date_selected,
         dates,
+        publish_at,
     } = form.into_inner();
+    let publish_at = publish_at.map(IntoInner::into_inner);
     let dates: Vec<Date> = dates
         .0
         .into_iter()
@@ -370,43 +1240,301 @@ pub async fn date_new_2_post(
         .map(|(date, _selected)| date)
         .collect();
 
-    let invalid = dates.iter().any(|date| date.from_date > date.to_date);
+    let rooms = sqlx::query_scalar!("select id from rooms")
+        .fetch_all(&mut *db)
+        .await?;
+    let date_types = DateType::get_variants(&mut db, &lang).await?;
+    let invalid = dates.len() > 1000
+        || dates.iter().any(|date| {
+            date.from_date >= date.to_date
+                || !rooms.contains(&date.room_id)
+                || !date_types
+                    .iter()
+                    .any(|date_type| date_type.value == date.date_type.value)
+        });
     if invalid {
-        return Err(anyhow!("Invalid buffered dates!").into());
+        return Ok(Err(Err(Status::BadRequest)));
+    }
+    if !dates
+        .iter()
+        .all(|date| admin.may_access(&date.date_type.value))
+    {
+        return Ok(Err(Err(Status::Forbidden)));
+    }
+    let conflicts = find_conflicts(&mut db, &dates, &lang).await?;
+    if conflicts.iter().any(Option::is_some) {
+        let messages = vec![Message {
+            text_key: String::from("date-conflicts"),
+            message_type: MessageType::Error,
+        }];
+        return Ok(Err(Ok(render_date_new_2(
+            lang, csrf, &dates, conflicts, publish_at, messages,
+        ))));
     }
 
-    let mut date_types = HashSet::new();
-    for date in dates {
-        let Date {
-            from_date,
-            to_date,
-            room_id,
-            date_type,
-        } = date;
-        date_types.insert(date_type.value.clone());
-        sqlx::query!(
-            "insert into dates (from_date, to_date, room_id, date_type) values ($1, $2, $3, $4)",
-            &from_date,
-            &to_date,
-            &room_id,
-            &date_type.value,
+    let publish_at = publish_at.unwrap_or_else(Local::now);
+    let mut tx = db.begin().await?;
+    let mut ids = Vec::new();
+    let mut created = Vec::new();
+    for date in &dates {
+        let id = match sqlx::query_scalar!(
+            "insert into dates (from_date, to_date, room_id, date_type, publish_at) \
+            values ($1, $2, $3, $4, $5) returning id",
+            &date.from_date,
+            &date.to_date,
+            &date.room_id,
+            &date.date_type.value,
+            &publish_at,
         )
-        .execute(&mut *db)
-        .await?;
+        .fetch_one(&mut *tx)
+        .await
+        {
+            Ok(id) => id,
+            // Someone else created an overlapping date in the meantime.
+            Err(sqlx::Error::Database(error)) if error.code().as_deref() == Some("23P01") => {
+                tx.rollback().await?;
+                let conflicts = find_conflicts(&mut db, &dates, &lang).await?;
+                let messages = vec![Message {
+                    text_key: String::from("date-conflicts"),
+                    message_type: MessageType::Error,
+                }];
+                return Ok(Err(Ok(render_date_new_2(
+                    lang,
+                    csrf,
+                    &dates,
+                    conflicts,
+                    Some(publish_at),
+                    messages,
+                ))));
+            }
+            Err(error) => return Err(error.into()),
+        };
+        ids.push(id.to_string());
+        created.push(json!({
+            "from_date": date.from_date,
+            "to_date": date.to_date,
+            "date_type": date.date_type.value,
+            "room_id": date.room_id,
+        }));
     }
+    audit::log(
+        &mut tx,
+        Some(&admin.id),
+        "date-create",
+        &ids,
+        json!({ "dates": created, "publish_at": publish_at }),
+    )
+    .await?;
 
-    for date_type in date_types.into_iter() {
-        waiting_list_notify(&mut db, &date_type, config, mailer).await?;
+    if publish_at > Local::now() {
+        jobs::enqueue(
+            &mut tx,
+            jobs::Kind::PublishDates,
+            publish_at.with_timezone(&Utc),
+        )
+        .await?;
+    } else {
+        notify_published_dates(&mut tx, config).await?;
     }
+    tx.commit().await?;
+
+    Ok(Ok(Redirect::to(uri!(dashboard(
+        day = Option::<&str>::None
+    )))))
+}
+
+#[get("/admin/date-import")]
+pub async fn date_import_get(
+    lang: Language,
+    csrf: CsrfToken,
+    _admin: Authorized<ManageDates>,
+) -> Template {
+    Template::render(
+        "date-import",
+        context! {
+            csrf_token: csrf.as_str(),
+            lang: lang.into_string(),
+            publish_at: Option::<DateTime<Local>>::None,
+        },
+    )
+}
+
+#[derive(FromForm)]
+pub struct DateImportForm<'r> {
+    file: TempFile<'r>,
+    publish_at: Option<FormDateTime>,
+}
 
-    Ok(Redirect::to(uri!(dashboard(day = Option::<&str>::None))))
+/// A line of an imported file as shown in the preview.
+#[derive(Serialize)]
+pub struct ImportRow {
+    row: usize,
+    fields: Vec<String>,
+    /// Text keys of the problems with this row, which isn't imported if there are any.
+    errors: Vec<&'static str>,
+}
+
+const IMPORT_DATETIME_FORMATS: [&str; 3] = ["%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M", "%d.%m.%Y %H:%M"];
+
+fn parse_import_datetime(value: &str) -> Option<DateTime<Local>> {
+    IMPORT_DATETIME_FORMATS
+        .into_iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value.trim(), format).ok())
+        .and_then(|datetime| datetime.and_local_timezone(Local).earliest())
+}
+
+/// Reads dates from a CSV file with the columns date type, room, start and end and shows them
+/// for confirmation, which creates them via [`date_new_2_post`].
+#[post("/admin/date-import", data = "<form>")]
+pub async fn date_import_post(
+    lang: Language,
+    csrf: CsrfToken,
+    admin: Authorized<ManageDates>,
+    mut db: Connection<Database>,
+    form: CsrfForm<DateImportForm<'_>>,
+) -> RocketResult<Template> {
+    let lang = lang.into_string();
+    let DateImportForm { file, publish_at } = form.into_inner();
+    let publish_at = publish_at.map(IntoInner::into_inner);
+    // Files which can't be read as text are treated like empty files.
+    let content = match file.path() {
+        Some(path) => tokio::fs::read_to_string(path).await.unwrap_or_default(),
+        None => String::new(),
+    };
+
+    // The first line is a header, which tells whether the file uses semicolons like spreadsheet
+    // applications with German settings.
+    let header = content.lines().next().unwrap_or_default();
+    let separator = if header.matches(';').count() > header.matches(',').count() {
+        ';'
+    } else {
+        ','
+    };
+    let records = crate::csv::read_records(&content, separator);
+    let is_empty = |fields: &Vec<String>| fields.iter().all(String::is_empty);
+    let mut messages = Vec::new();
+    if records.iter().skip(1).all(is_empty) {
+        messages.push(Message {
+            text_key: String::from("date-import-invalid"),
+            message_type: MessageType::Error,
+        });
+    } else if records
+        .iter()
+        .skip(1)
+        .filter(|fields| !is_empty(fields))
+        .count()
+        > 1000
+    {
+        messages.push(Message {
+            text_key: String::from("too-many-dates"),
+            message_type: MessageType::Error,
+        });
+    }
+    if !messages.is_empty() {
+        return Ok(Template::render(
+            "date-import",
+            context! {
+                csrf_token: csrf.as_str(),
+                lang,
+                publish_at,
+                messages,
+            },
+        ));
+    }
+
+    let date_types = admin.accessible_date_types(&mut db, &lang).await?;
+    let rooms: HashMap<String, i32> = sqlx::query!("select id, room_number from rooms")
+        .fetch_all(&mut *db)
+        .await?
+        .into_iter()
+        .map(|record| (record.room_number, record.id))
+        .collect();
+    let mut rows = Vec::new();
+    let mut candidates: Vec<(usize, Date)> = Vec::new();
+    for (index, fields) in records.into_iter().enumerate().skip(1) {
+        if is_empty(&fields) {
+            continue;
+        }
+        let mut errors = Vec::new();
+        let mut date = None;
+        if let [date_type, room, from_date, to_date] = &fields[..] {
+            // Date types can be given by their identifier or their name.
+            let date_type = date_types.iter().find(|variant| {
+                variant.value == date_type.trim()
+                    || variant.display_name.as_deref().is_some_and(|display_name| {
+                        display_name.to_lowercase() == date_type.trim().to_lowercase()
+                    })
+            });
+            if date_type.is_none() {
+                errors.push("date-import-date-type");
+            }
+            let room_id = rooms.get(room.trim());
+            if room_id.is_none() {
+                errors.push("validation-room");
+            }
+            let from_date = parse_import_datetime(from_date);
+            let to_date = parse_import_datetime(to_date);
+            match (from_date, to_date) {
+                (Some(from_date), Some(to_date)) if from_date >= to_date => {
+                    errors.push("wrong-date-order")
+                }
+                (Some(_), Some(_)) => {}
+                _ => errors.push("date-import-datetime"),
+            }
+            if let (Some(date_type), Some(room_id), Some(from_date), Some(to_date)) =
+                (date_type, room_id, from_date, to_date)
+            {
+                date = Some(Date {
+                    from_date,
+                    to_date,
+                    room_id: *room_id,
+                    date_type: date_type.clone(),
+                });
+            }
+        } else {
+            errors.push("date-import-columns");
+        }
+        if let (Some(date), true) = (date, errors.is_empty()) {
+            candidates.push((rows.len(), date));
+        }
+        rows.push(ImportRow {
+            row: index + 1,
+            fields,
+            errors,
+        });
+    }
+
+    let (row_indices, candidates): (Vec<usize>, Vec<Date>) = candidates.into_iter().unzip();
+    let conflicts = find_conflicts(&mut db, &candidates, &lang).await?;
+    let mut dates: Vec<Date> = Vec::new();
+    for ((row, date), conflict) in row_indices.into_iter().zip(candidates).zip(conflicts) {
+        match conflict {
+            Some(Conflict::Existing { .. }) => {
+                rows[row].errors.push("date-import-overlap-existing")
+            }
+            Some(Conflict::New) => rows[row].errors.push("date-import-overlap-file"),
+            None => dates.push(date),
+        }
+    }
+
+    Ok(Template::render(
+        "date-import-preview",
+        context! {
+            csrf_token: csrf.as_str(),
+            lang,
+            rows,
+            dates,
+            publish_at,
+        },
+    ))
 }
 
 #[get("/admin/room-manage")]
 pub async fn room_manage_get(
     lang: Language,
+    csrf: CsrfToken,
     mut db: Connection<Database>,
-    _admin: Admin,
+    _admin: Authorized<ManageRooms>,
 ) -> RocketResult<Template> {
     let rooms: Vec<Room> = sqlx::query_as!(Room, "select id, room_number from rooms")
         .fetch_all(&mut *db)
@@ -414,6 +1542,7 @@ pub async fn room_manage_get(
     Ok(Template::render(
         "room-manage",
         context! {
+            csrf_token: csrf.as_str(),
             lang: lang.into_string(),
             rooms
         },
@@ -429,9 +1558,10 @@ pub struct RoomManageForm<'r> {
 #[post("/admin/room-manage", data = "<form>")]
 pub async fn room_manage_post(
     lang: Language,
+    csrf: CsrfToken,
     mut db: Connection<Database>,
-    _admin: Admin,
-    form: Form<RoomManageForm<'_>>,
+    admin: Authorized<ManageRooms>,
+    form: CsrfForm<RoomManageForm<'_>>,
 ) -> RocketResult<Template> {
     let RoomManageForm {
         room_number,
@@ -439,9 +1569,20 @@ pub async fn room_manage_post(
     } = form.into_inner();
     let mut messages = Vec::new();
     if button == "create" {
-        sqlx::query!("insert into rooms (room_number) values ($1)", &room_number)
-            .execute(&mut *db)
-            .await?;
+        let id = sqlx::query_scalar!(
+            "insert into rooms (room_number) values ($1) returning id",
+            &room_number
+        )
+        .fetch_one(&mut *db)
+        .await?;
+        audit::log(
+            &mut db,
+            Some(&admin.id),
+            "room-create",
+            &[id.to_string()],
+            json!({ "room_number": room_number }),
+        )
+        .await?;
         messages.push(Message {
             text_key: String::from("room-created"),
             message_type: MessageType::Success,
@@ -450,9 +1591,18 @@ pub async fn room_manage_post(
         let dash_position = button.chars().position(|c| c == '-').unwrap();
         let id_str: String = button.chars().skip(dash_position + 1).collect();
         let id = id_str.parse::<i32>()?;
-        sqlx::query!("delete from rooms where id = $1", &id)
-            .execute(&mut *db)
-            .await?;
+        let room_number =
+            sqlx::query_scalar!("delete from rooms where id = $1 returning room_number", &id)
+                .fetch_optional(&mut *db)
+                .await?;
+        audit::log(
+            &mut db,
+            Some(&admin.id),
+            "room-delete",
+            &[id.to_string()],
+            json!({ "room_number": room_number }),
+        )
+        .await?;
         messages.push(Message {
             text_key: String::from("room-deleted"),
             message_type: MessageType::Success,
@@ -470,6 +1620,7 @@ pub async fn room_manage_post(
     Ok(Template::render(
         "room-manage",
         context! {
+            csrf_token: csrf.as_str(),
             lang: lang.into_string(),
             rooms,
             messages,
@@ -493,6 +1644,8 @@ pub struct Announcement {
 #[get("/admin/announcements")]
 pub async fn announcements_get(
     lang: Language,
+    csrf: CsrfToken,
+    admin: Authorized<ManageAnnouncements>,
     mut db: Connection<Database>,
 ) -> RocketResult<Template> {
     let announcements = sqlx::query_as!(
@@ -501,9 +1654,15 @@ pub async fn announcements_get(
         from announcements
         order by position, lang"#
     ).fetch_all(&mut *db).await?;
+    // Restricted admins may only edit the announcements of their date types.
+    let announcements: Vec<Announcement> = announcements
+        .into_iter()
+        .filter(|announcement| admin.may_access(&announcement.position))
+        .collect();
     Ok(Template::render(
         "announcements",
         context! {
+            csrf_token: csrf.as_str(),
             lang: lang.into_string(),
             announcements
         },
@@ -512,15 +1671,33 @@ pub async fn announcements_get(
 
 #[post("/admin/announcements", data = "<form>")]
 pub async fn announcements_post(
+    admin: Authorized<ManageAnnouncements>,
     mut db: Connection<Database>,
-    form: Form<AnnouncementsForm<'_>>,
-) -> RocketResult<Redirect> {
+    form: CsrfForm<AnnouncementsForm<'_>>,
+) -> RocketResult<Result<Redirect, Status>> {
     let AnnouncementsForm { announcements } = form.into_inner();
+    if !announcements
+        .keys()
+        .all(|position| admin.may_access(position))
+    {
+        return Ok(Err(Status::Forbidden));
+    }
+    audit::log(
+        &mut db,
+        Some(&admin.id),
+        "announcements-update",
+        &announcements
+            .keys()
+            .map(|position| position.to_string())
+            .collect::<Vec<_>>(),
+        json!({ "announcements": &announcements }),
+    )
+    .await?;
     for (p, map) in announcements {
         for (l, c) in map {
             sqlx::query!(
                 "update announcements set content = $1 \
-                where position = ($2::text)::announcement_position and lang = ($3::text)::language",
+                where position = $2 and lang = ($3::text)::language",
                 &c,
                 &p,
                 &l,
@@ -529,5 +1706,1365 @@ pub async fn announcements_post(
             .await?;
         }
     }
-    Ok(Redirect::to(uri!(announcements_get)))
+    Ok(Ok(Redirect::to(uri!(announcements_get))))
+}
+
+#[derive(Serialize)]
+pub struct DateTypeEntry {
+    id: String,
+    sort_order: i32,
+    display_names: serde_json::Value,
+    dates: i64,
+}
+
+#[derive(Serialize)]
+pub struct VoiceEntry {
+    id: i32,
+    value: String,
+    position: VoicePosition,
+    sort_order: i32,
+    display_names: serde_json::Value,
+    bookings: i64,
+}
+
+async fn render_date_types(
+    lang: String,
+    csrf: CsrfToken,
+    db: &mut Connection<Database>,
+    messages: Vec<Message>,
+) -> anyhow::Result<Template> {
+    let date_types = sqlx::query_as!(
+        DateTypeEntry,
+        r#"select
+            id,
+            sort_order,
+            coalesce(
+                (select jsonb_object_agg(lang, display_name) from date_types_translations where date_type = date_types.id),
+                '{}'
+            ) as "display_names!",
+            (select count(*) from dates where date_type = date_types.id) as "dates!"
+        from date_types
+        order by sort_order, id"#
+    )
+    .fetch_all(&mut **db)
+    .await?;
+    Ok(Template::render(
+        "date-types",
+        context! {
+            csrf_token: csrf.as_str(),
+            lang,
+            date_types,
+            messages,
+        },
+    ))
+}
+
+async fn render_date_type(
+    lang: String,
+    csrf: CsrfToken,
+    db: &mut Connection<Database>,
+    id: &str,
+    messages: Vec<Message>,
+) -> anyhow::Result<Option<Template>> {
+    let date_type = match sqlx::query_as!(
+        DateTypeEntry,
+        r#"select
+            id,
+            sort_order,
+            coalesce(
+                (select jsonb_object_agg(lang, display_name) from date_types_translations where date_type = date_types.id),
+                '{}'
+            ) as "display_names!",
+            (select count(*) from dates where date_type = date_types.id) as "dates!"
+        from date_types
+        where id = $1"#,
+        &id
+    )
+    .fetch_optional(&mut **db)
+    .await?
+    {
+        Some(date_type) => date_type,
+        None => return Ok(None),
+    };
+    let voices = sqlx::query_as!(
+        VoiceEntry,
+        r#"select
+            id,
+            value,
+            position as "position: VoicePosition",
+            sort_order,
+            coalesce(
+                (select jsonb_object_agg(lang, display_name) from voices_translations where voice = voices.id),
+                '{}'
+            ) as "display_names!",
+            (select count(*) from bookings where voice = voices.id) as "bookings!"
+        from voices
+        where date_type = $1
+        order by position, sort_order, id"#,
+        &id
+    )
+    .fetch_all(&mut **db)
+    .await?;
+    Ok(Some(Template::render(
+        "date-type",
+        context! {
+            csrf_token: csrf.as_str(),
+            lang,
+            date_type,
+            voices,
+            positions: VoicePosition::ALL,
+            messages,
+        },
+    )))
+}
+
+/// Checks that an identifier of a date type or voice can be used in URLs and form values.
+fn validate_identifier(value: &str, messages: &mut Vec<Message>) {
+    if !regex_is_match!("^[a-z0-9]+(-[a-z0-9]+)*$", value) {
+        messages.push(Message {
+            text_key: String::from("validation-identifier"),
+            message_type: MessageType::Error,
+        });
+    }
+}
+
+/// Checks that a display name has been entered for every supported language.
+fn validate_display_names(display_names: &BTreeMap<&str, &str>, messages: &mut Vec<Message>) {
+    let complete = SUPPORTED_LANGUAGES.iter().all(|lang| {
+        display_names
+            .get(lang)
+            .is_some_and(|display_name| !display_name.trim().is_empty())
+    });
+    if !complete {
+        messages.push(Message {
+            text_key: String::from("validation-display-names"),
+            message_type: MessageType::Error,
+        });
+    }
+}
+
+#[get("/admin/date-types")]
+pub async fn date_types_get(
+    lang: Language,
+    csrf: CsrfToken,
+    _admin: Authorized<ManageDateTypes>,
+    mut db: Connection<Database>,
+) -> RocketResult<Template> {
+    Ok(render_date_types(lang.into_string(), csrf, &mut db, Vec::new()).await?)
+}
+
+#[derive(FromForm)]
+pub struct DateTypesForm<'r> {
+    id: &'r str,
+    display_names: BTreeMap<&'r str, &'r str>,
+    sort_order: Option<i32>,
+    button: &'r str,
+}
+
+#[post("/admin/date-types", data = "<form>")]
+pub async fn date_types_post(
+    lang: Language,
+    csrf: CsrfToken,
+    admin: Authorized<ManageDateTypes>,
+    mut db: Connection<Database>,
+    form: CsrfForm<DateTypesForm<'_>>,
+) -> RocketResult<Template> {
+    let lang = lang.into_string();
+    let DateTypesForm {
+        id,
+        display_names,
+        sort_order,
+        button,
+    } = form.into_inner();
+    let mut messages = Vec::new();
+
+    if button == "create" {
+        let id = id.trim();
+        validate_identifier(id, &mut messages);
+        validate_display_names(&display_names, &mut messages);
+        // "general" is the position of the announcement on the start page.
+        if id == "general" {
+            messages.push(Message {
+                text_key: String::from("date-type-exists"),
+                message_type: MessageType::Error,
+            });
+        }
+        if !messages.is_empty() {
+            return Ok(render_date_types(lang, csrf, &mut db, messages).await?);
+        }
+
+        let sort_order = sort_order.unwrap_or_default();
+        let mut tx = db.begin().await?;
+        match sqlx::query!(
+            "insert into date_types (id, sort_order) values ($1, $2)",
+            &id,
+            sort_order
+        )
+        .execute(&mut *tx)
+        .await
+        {
+            Ok(_) => {}
+            Err(sqlx::Error::Database(error)) if error.code().as_deref() == Some("23505") => {
+                tx.rollback().await?;
+                let messages = vec![Message {
+                    text_key: String::from("date-type-exists"),
+                    message_type: MessageType::Error,
+                }];
+                return Ok(render_date_types(lang, csrf, &mut db, messages).await?);
+            }
+            Err(error) => return Err(error.into()),
+        }
+        for language in SUPPORTED_LANGUAGES {
+            let display_name = display_names[language].trim();
+            sqlx::query!(
+                "insert into date_types_translations (date_type, lang, display_name) values ($1, $2, $3)",
+                &id,
+                language,
+                display_name,
+            )
+            .execute(&mut *tx)
+            .await?;
+            let args: HashMap<&str, FluentValue> = hash_map! { "date_type" => display_name.into() };
+            let description = LOCALES
+                .lookup_single_language(
+                    &language.parse()?,
+                    "announcement-date-type-description",
+                    Some(&args),
+                )
+                .ok_or_else(|| {
+                    anyhow!("Missing translation for announcement-date-type-description!")
+                })?
+                // The description is stored as plain text, so Fluent's bidi isolation marks are removed.
+                .replace(['\u{2068}', '\u{2069}'], "");
+            sqlx::query!(
+                "insert into announcements (lang, position, description) \
+                values (($1::text)::language, $2, $3) \
+                on conflict (lang, position) do nothing",
+                language,
+                &id,
+                &description,
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        audit::log(
+            &mut tx,
+            Some(&admin.id),
+            "date-type-create",
+            &[String::from(id)],
+            json!({ "display_names": display_names, "sort_order": sort_order }),
+        )
+        .await?;
+        tx.commit().await?;
+        messages.push(Message {
+            text_key: String::from("date-type-created"),
+            message_type: MessageType::Success,
+        });
+    } else if let Some(id) = button.strip_prefix("delete-") {
+        let dates = sqlx::query_scalar!(
+            r#"select count(*) as "count!" from dates where date_type = $1"#,
+            &id
+        )
+        .fetch_one(&mut *db)
+        .await?;
+        // Admins without date types may access all of them, so removing the last date type of a
+        // restricted admin would grant them access to everything.
+        let scoped = sqlx::query_scalar!(
+            r#"select
+            exists (select from admins_date_types where date_type = $1)
+            or exists (select from invites where $1 = any(date_types)) as "scoped!""#,
+            &id
+        )
+        .fetch_one(&mut *db)
+        .await?;
+        if dates > 0 {
+            messages.push(Message {
+                text_key: String::from("date-type-in-use"),
+                message_type: MessageType::Error,
+            });
+        } else if scoped {
+            messages.push(Message {
+                text_key: String::from("date-type-has-admins"),
+                message_type: MessageType::Error,
+            });
+        } else {
+            sqlx::query!("delete from announcements where position = $1", &id)
+                .execute(&mut *db)
+                .await?;
+            sqlx::query!("delete from date_types where id = $1", &id)
+                .execute(&mut *db)
+                .await?;
+            audit::log(
+                &mut db,
+                Some(&admin.id),
+                "date-type-delete",
+                &[String::from(id)],
+                json!({}),
+            )
+            .await?;
+            messages.push(Message {
+                text_key: String::from("date-type-deleted"),
+                message_type: MessageType::Success,
+            });
+        }
+    } else {
+        messages.push(Message {
+            text_key: String::from("validation-unknown"),
+            message_type: MessageType::Error,
+        });
+    }
+
+    Ok(render_date_types(lang, csrf, &mut db, messages).await?)
+}
+
+#[get("/admin/date-types/<id>")]
+pub async fn date_type_get(
+    lang: Language,
+    csrf: CsrfToken,
+    _admin: Authorized<ManageDateTypes>,
+    mut db: Connection<Database>,
+    id: &str,
+) -> RocketResult<Option<Template>> {
+    Ok(render_date_type(lang.into_string(), csrf, &mut db, id, Vec::new()).await?)
+}
+
+#[derive(FromForm)]
+pub struct DateTypeForm<'r> {
+    display_names: BTreeMap<&'r str, &'r str>,
+    sort_order: Option<i32>,
+    value: Option<&'r str>,
+    position: Option<VoicePosition>,
+    button: &'r str,
+}
+
+#[post("/admin/date-types/<id>", data = "<form>")]
+pub async fn date_type_post(
+    lang: Language,
+    csrf: CsrfToken,
+    admin: Authorized<ManageDateTypes>,
+    mut db: Connection<Database>,
+    id: &str,
+    form: CsrfForm<DateTypeForm<'_>>,
+) -> RocketResult<Result<Template, Status>> {
+    let lang = lang.into_string();
+    let DateTypeForm {
+        display_names,
+        sort_order,
+        value,
+        position,
+        button,
+    } = form.into_inner();
+    let exists = sqlx::query_scalar!(
+        r#"select exists(select 1 from date_types where id = $1) as "exists!""#,
+        &id
+    )
+    .fetch_one(&mut *db)
+    .await?;
+    if !exists {
+        return Ok(Err(Status::NotFound));
+    }
+    let sort_order = sort_order.unwrap_or_default();
+    let mut messages = Vec::new();
+
+    if button == "update" {
+        validate_display_names(&display_names, &mut messages);
+        if messages.is_empty() {
+            sqlx::query!(
+                "update date_types set sort_order = $2 where id = $1",
+                &id,
+                sort_order
+            )
+            .execute(&mut *db)
+            .await?;
+            for language in SUPPORTED_LANGUAGES {
+                sqlx::query!(
+                    "insert into date_types_translations (date_type, lang, display_name) values ($1, $2, $3) \
+                    on conflict (date_type, lang) do update set display_name = excluded.display_name",
+                    &id,
+                    language,
+                    display_names[language].trim(),
+                )
+                .execute(&mut *db)
+                .await?;
+            }
+            audit::log(
+                &mut db,
+                Some(&admin.id),
+                "date-type-update",
+                &[String::from(id)],
+                json!({ "display_names": display_names, "sort_order": sort_order }),
+            )
+            .await?;
+            messages.push(Message {
+                text_key: String::from("date-type-updated"),
+                message_type: MessageType::Success,
+            });
+        }
+    } else if button == "voice-create" {
+        let value = value.unwrap_or_default().trim();
+        validate_identifier(value, &mut messages);
+        validate_display_names(&display_names, &mut messages);
+        let position = match position {
+            Some(position) => position,
+            None => {
+                messages.push(Message {
+                    text_key: String::from("validation-select"),
+                    message_type: MessageType::Error,
+                });
+                VoicePosition::Booking
+            }
+        };
+        let exists = sqlx::query_scalar!(
+            r#"select exists(
+                select 1 from voices where value = $1 and date_type = $2 and position = $3
+            ) as "exists!""#,
+            &value,
+            &id,
+            position as VoicePosition,
+        )
+        .fetch_one(&mut *db)
+        .await?;
+        if exists {
+            messages.push(Message {
+                text_key: String::from("voice-exists"),
+                message_type: MessageType::Error,
+            });
+        }
+        if messages.is_empty() {
+            let voice = sqlx::query_scalar!(
+                "insert into voices (value, date_type, position, sort_order) values ($1, $2, $3, $4) returning id",
+                &value,
+                &id,
+                position as VoicePosition,
+                sort_order,
+            )
+            .fetch_one(&mut *db)
+            .await?;
+            for language in SUPPORTED_LANGUAGES {
+                sqlx::query!(
+                    "insert into voices_translations (voice, lang, display_name) values ($1, $2, $3)",
+                    voice,
+                    language,
+                    display_names[language].trim(),
+                )
+                .execute(&mut *db)
+                .await?;
+            }
+            audit::log(
+                &mut db,
+                Some(&admin.id),
+                "voice-create",
+                &[String::from(id), voice.to_string()],
+                json!({
+                    "value": value,
+                    "position": position,
+                    "display_names": display_names,
+                    "sort_order": sort_order,
+                }),
+            )
+            .await?;
+            messages.push(Message {
+                text_key: String::from("voice-created"),
+                message_type: MessageType::Success,
+            });
+        }
+    } else if let Some(voice) = button.strip_prefix("voice-update-") {
+        let voice = match voice.parse::<i32>() {
+            Ok(voice) => voice,
+            Err(_) => return Ok(Err(Status::BadRequest)),
+        };
+        validate_display_names(&display_names, &mut messages);
+        if messages.is_empty() {
+            let updated = sqlx::query!(
+                "update voices set sort_order = $3 where id = $1 and date_type = $2",
+                voice,
+                &id,
+                sort_order,
+            )
+            .execute(&mut *db)
+            .await?
+            .rows_affected();
+            if updated == 0 {
+                return Ok(Err(Status::NotFound));
+            }
+            for language in SUPPORTED_LANGUAGES {
+                sqlx::query!(
+                    "insert into voices_translations (voice, lang, display_name) values ($1, $2, $3) \
+                    on conflict (voice, lang) do update set display_name = excluded.display_name",
+                    voice,
+                    language,
+                    display_names[language].trim(),
+                )
+                .execute(&mut *db)
+                .await?;
+            }
+            audit::log(
+                &mut db,
+                Some(&admin.id),
+                "voice-update",
+                &[String::from(id), voice.to_string()],
+                json!({ "display_names": display_names, "sort_order": sort_order }),
+            )
+            .await?;
+            messages.push(Message {
+                text_key: String::from("voice-updated"),
+                message_type: MessageType::Success,
+            });
+        }
+    } else if let Some(voice) = button.strip_prefix("voice-delete-") {
+        let voice = match voice.parse::<i32>() {
+            Ok(voice) => voice,
+            Err(_) => return Ok(Err(Status::BadRequest)),
+        };
+        // Deleting a voice would also delete the bookings made with it. The voice is locked, so
+        // no booking can be made with it until it is deleted.
+        let mut tx = db.begin().await?;
+        let value = match sqlx::query_scalar!(
+            "select value from voices where id = $1 and date_type = $2 for update",
+            voice,
+            &id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        {
+            Some(value) => value,
+            None => return Ok(Err(Status::NotFound)),
+        };
+        let bookings = sqlx::query_scalar!(
+            r#"select count(*) as "count!" from bookings where voice = $1"#,
+            voice
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if bookings > 0 {
+            messages.push(Message {
+                text_key: String::from("voice-in-use"),
+                message_type: MessageType::Error,
+            });
+        } else {
+            sqlx::query!("delete from voices where id = $1", voice)
+                .execute(&mut *tx)
+                .await?;
+            audit::log(
+                &mut tx,
+                Some(&admin.id),
+                "voice-delete",
+                &[String::from(id), voice.to_string()],
+                json!({ "value": value }),
+            )
+            .await?;
+            tx.commit().await?;
+            messages.push(Message {
+                text_key: String::from("voice-deleted"),
+                message_type: MessageType::Success,
+            });
+        }
+    } else {
+        messages.push(Message {
+            text_key: String::from("validation-unknown"),
+            message_type: MessageType::Error,
+        });
+    }
+
+    Ok(render_date_type(lang, csrf, &mut db, id, messages)
+        .await?
+        .ok_or(Status::NotFound))
+}
+
+#[derive(Serialize)]
+pub struct DateTypeSettingsEntry {
+    date_type: DateType,
+    settings: DateTypeSettings,
+}
+
+async fn render_date_type_settings(
+    lang: String,
+    csrf: CsrfToken,
+    db: &mut Connection<Database>,
+    admin: &Admin,
+    messages: Vec<Message>,
+) -> anyhow::Result<Template> {
+    let mut date_types = Vec::new();
+    for date_type in admin.accessible_date_types(db, &lang).await? {
+        let settings = DateTypeSettings::get(db, &date_type.value).await?;
+        date_types.push(DateTypeSettingsEntry {
+            date_type,
+            settings,
+        });
+    }
+    Ok(Template::render(
+        "date-type-settings",
+        context! {
+            csrf_token: csrf.as_str(),
+            lang,
+            date_types,
+            messages,
+        },
+    ))
+}
+
+#[get("/admin/date-type-settings")]
+pub async fn date_type_settings_get(
+    lang: Language,
+    csrf: CsrfToken,
+    admin: Authorized<ManageDates>,
+    mut db: Connection<Database>,
+) -> RocketResult<Template> {
+    Ok(render_date_type_settings(lang.into_string(), csrf, &mut db, &admin, Vec::new()).await?)
+}
+
+#[derive(FromForm)]
+pub struct DateTypeSettingsForm<'r> {
+    date_type: &'r str,
+    enabled: bool,
+    opens_at: Option<FormDateTime>,
+    application_deadline: Option<FormDateTime>,
+    #[field(validate = range(..=365))]
+    days_deadline: u32,
+    #[field(validate = range(..=1000))]
+    dates_per_day: u32,
+    reminder_hours: &'r str,
+}
+
+/// Parses a comma-separated list of reminder offsets in hours, latest reminder last.
+fn parse_reminder_hours(value: &str) -> Option<Vec<i32>> {
+    let mut hours = value
+        .split(',')
+        .map(str::trim)
+        .filter(|hours| !hours.is_empty())
+        .map(|hours| hours.parse().ok().filter(|hours| (1..=720).contains(hours)))
+        .collect::<Option<Vec<i32>>>()?;
+    hours.sort_unstable_by(|a, b| b.cmp(a));
+    hours.dedup();
+    Some(hours)
+}
+
+#[post("/admin/date-type-settings", data = "<form>")]
+pub async fn date_type_settings_post(
+    lang: Language,
+    csrf: CsrfToken,
+    admin: Authorized<ManageDates>,
+    mut db: Connection<Database>,
+    form: CsrfForm<DateTypeSettingsForm<'_>>,
+) -> RocketResult<Result<Template, Status>> {
+    let lang = lang.into_string();
+    let DateTypeSettingsForm {
+        date_type,
+        enabled,
+        opens_at,
+        application_deadline,
+        days_deadline,
+        dates_per_day,
+        reminder_hours,
+    } = form.into_inner();
+    if !admin.may_access(date_type) {
+        return Ok(Err(Status::Forbidden));
+    }
+    let opens_at = opens_at.map(IntoInner::into_inner);
+    let application_deadline = application_deadline.map(IntoInner::into_inner);
+
+    let mut messages = Vec::new();
+    if let (Some(opens_at), Some(application_deadline)) = (opens_at, application_deadline) {
+        if opens_at >= application_deadline {
+            messages.push(Message {
+                text_key: String::from("opens-after-deadline"),
+                message_type: MessageType::Error,
+            });
+        }
+    }
+    let reminder_hours = parse_reminder_hours(reminder_hours).unwrap_or_else(|| {
+        messages.push(Message {
+            text_key: String::from("validation-reminder-hours"),
+            message_type: MessageType::Error,
+        });
+        Vec::new()
+    });
+    if messages.is_empty() {
+        let updated = sqlx::query!(
+            "update date_types \
+            set enabled = $2, opens_at = $3, application_deadline = $4, days_deadline = $5, dates_per_day = $6, \
+            reminder_hours = $7 \
+            where id = $1",
+            &date_type,
+            enabled,
+            opens_at,
+            application_deadline,
+            days_deadline as i32,
+            dates_per_day as i32,
+            &reminder_hours,
+        )
+        .execute(&mut *db)
+        .await?
+        .rows_affected();
+        if updated == 0 {
+            return Ok(Err(Status::NotFound));
+        }
+        audit::log(
+            &mut db,
+            Some(&admin.id),
+            "date-type-settings-update",
+            &[String::from(date_type)],
+            json!({
+                "enabled": enabled,
+                "opens_at": opens_at,
+                "application_deadline": application_deadline,
+                "days_deadline": days_deadline,
+                "dates_per_day": dates_per_day,
+                "reminder_hours": reminder_hours,
+            }),
+        )
+        .await?;
+        messages.push(Message {
+            text_key: String::from("date-type-settings-saved"),
+            message_type: MessageType::Success,
+        });
+    }
+
+    Ok(Ok(render_date_type_settings(
+        lang, csrf, &mut db, &admin, messages,
+    )
+    .await?))
+}
+
+#[derive(Serialize)]
+pub struct ResultBooking {
+    token: String,
+    person_name: String,
+    from_date: DateTime<Local>,
+    voice: String,
+    date_type: DateType,
+}
+
+async fn get_result_booking(
+    db: &mut Connection<Database>,
+    token: &str,
+    lang: &str,
+) -> anyhow::Result<Option<ResultBooking>> {
+    Ok(sqlx::query!(
+        r#"select
+        bookings.token,
+        person_name,
+        from_date,
+        voices_translations.display_name as voice,
+        dates.date_type,
+        date_types_translations.display_name as date_type_display_name
+        from bookings
+        join dates on dates.id = bookings.date_id
+        join voices on voices.id = bookings.voice
+        join voices_translations on voices_translations.voice = voices.id
+        join date_types_translations on date_types_translations.date_type = dates.date_type
+        where bookings.token = $1
+        and voices_translations.lang = $2
+        and date_types_translations.lang = $2"#,
+        &token,
+        &lang
+    )
+    .fetch_optional(&mut **db)
+    .await?
+    .map(|record| ResultBooking {
+        token: record.token,
+        person_name: record.person_name,
+        from_date: record.from_date.with_timezone(&Local),
+        voice: record.voice,
+        date_type: DateType {
+            value: record.date_type,
+            display_name: Some(record.date_type_display_name),
+        },
+    }))
+}
+
+#[get("/admin/booking-result/<token>")]
+pub async fn booking_result_get(
+    lang: Language,
+    csrf: CsrfToken,
+    admin: Authorized<RecordResults>,
+    mut db: Connection<Database>,
+    token: &str,
+) -> RocketResult<Result<Template, Status>> {
+    let lang = lang.into_string();
+    let booking = match get_result_booking(&mut db, token, &lang).await? {
+        Some(booking) => booking,
+        None => return Ok(Err(Status::NotFound)),
+    };
+    if !admin.may_access(&booking.date_type.value) {
+        return Ok(Err(Status::Forbidden));
+    }
+    let voices = booking
+        .date_type
+        .get_voices(&mut db, &lang, "result")
+        .await?;
+    let result = sqlx::query!(
+        r#"select outcome::text as "outcome!", voices.value as "voice?", booking_results.notes, mailed
+        from booking_results
+        left join voices on voices.id = booking_results.voice
+        where booking = $1"#,
+        &token
+    )
+    .fetch_optional(&mut *db)
+    .await?;
+    let mut messages = Vec::new();
+    let (outcome_selected, voice_selected, notes) = match result {
+        Some(record) => {
+            if record.mailed.is_some() {
+                messages.push(Message {
+                    text_key: String::from("result-already-mailed"),
+                    message_type: MessageType::Info,
+                });
+            }
+            (
+                record.outcome,
+                record.voice.unwrap_or_default(),
+                record.notes,
+            )
+        }
+        None => Default::default(),
+    };
+    Ok(Ok(Template::render(
+        "booking-result",
+        context! {
+            csrf_token: csrf.as_str(),
+            lang,
+            booking,
+            voices,
+            outcomes: RESULT_OUTCOMES,
+            outcome_selected,
+            voice_selected,
+            notes,
+            messages,
+        },
+    )))
+}
+
+#[derive(FromForm)]
+pub struct BookingResultForm<'r> {
+    outcome: &'r str,
+    voice: &'r str,
+    notes: &'r str,
+}
+
+#[post("/admin/booking-result/<token>", data = "<form>")]
+pub async fn booking_result_post(
+    lang: Language,
+    csrf: CsrfToken,
+    admin: Authorized<RecordResults>,
+    mut db: Connection<Database>,
+    token: &str,
+    form: CsrfForm<BookingResultForm<'_>>,
+) -> RocketResult<Result<Redirect, Result<Template, Status>>> {
+    let lang = lang.into_string();
+    let BookingResultForm {
+        outcome,
+        voice,
+        notes,
+    } = form.into_inner();
+    let booking = match get_result_booking(&mut db, token, &lang).await? {
+        Some(booking) => booking,
+        None => return Ok(Err(Err(Status::NotFound))),
+    };
+    if !admin.may_access(&booking.date_type.value) {
+        return Ok(Err(Err(Status::Forbidden)));
+    }
+
+    let mut messages = Vec::new();
+    if !RESULT_OUTCOMES.contains(&outcome) {
+        messages.push(Message {
+            text_key: String::from("validation-select"),
+            message_type: MessageType::Error,
+        });
+    }
+    let voice_id = match voice {
+        "" => None,
+        voice => {
+            let voice_id = sqlx::query_scalar!(
+                "select id from voices where value = $1 and date_type = $2 and position = 'result'",
+                &voice,
+                &booking.date_type.value,
+            )
+            .fetch_optional(&mut *db)
+            .await?;
+            if voice_id.is_none() {
+                messages.push(Message {
+                    text_key: String::from("validation-select"),
+                    message_type: MessageType::Error,
+                });
+            }
+            voice_id
+        }
+    };
+
+    if !messages.is_empty() {
+        let voices = booking
+            .date_type
+            .get_voices(&mut db, &lang, "result")
+            .await?;
+        return Ok(Err(Ok(Template::render(
+            "booking-result",
+            context! {
+                csrf_token: csrf.as_str(),
+                lang,
+                booking,
+                voices,
+                outcomes: RESULT_OUTCOMES,
+                outcome_selected: outcome,
+                voice_selected: voice,
+                notes,
+                messages,
+            },
+        ))));
+    }
+
+    sqlx::query!(
+        r#"insert into booking_results (booking, outcome, voice, notes, updated)
+        values ($1, ($2::text)::result_outcome, $3, $4, now())
+        on conflict (booking) do update
+        set outcome = excluded.outcome, voice = excluded.voice, notes = excluded.notes, updated = now(),
+        -- a changed result has to be mailed again
+        mailed = case
+            when booking_results.outcome = excluded.outcome
+                and booking_results.voice is not distinct from excluded.voice
+            then booking_results.mailed
+        end"#,
+        &token,
+        &outcome,
+        voice_id,
+        &notes,
+    )
+    .execute(&mut *db)
+    .await?;
+    audit::log(
+        &mut db,
+        Some(&admin.id),
+        "result-record",
+        &[String::from(token)],
+        json!({ "outcome": outcome, "voice": voice, "notes": notes }),
+    )
+    .await?;
+
+    let day = format!(
+        "{}",
+        datetime_to_day(booking.from_date)
+            .naive_local()
+            .format(crate::BROWSER_DATETIME_FORMAT)
+    );
+    Ok(Ok(Redirect::to(uri!(dashboard(day = Some(day))))))
+}
+
+#[derive(Serialize)]
+pub struct PendingResultMails {
+    date_type: DateType,
+    count: i64,
+}
+
+async fn get_pending_result_mails(
+    db: &mut Connection<Database>,
+    admin: &Admin,
+    lang: &str,
+) -> anyhow::Result<Vec<PendingResultMails>> {
+    Ok(sqlx::query!(
+        r#"select dates.date_type, display_name, count(*) as "count!"
+        from booking_results
+        join bookings on bookings.token = booking_results.booking
+        join dates on dates.id = bookings.date_id
+        join date_types_translations on date_types_translations.date_type = dates.date_type
+        where mailed is null
+        and date_types_translations.lang = $1
+        and ($2::text[] is null or dates.date_type = any($2))
+        group by dates.date_type, display_name
+        order by dates.date_type"#,
+        &lang,
+        admin.date_types.as_deref(),
+    )
+    .fetch_all(&mut **db)
+    .await?
+    .into_iter()
+    .map(|record| PendingResultMails {
+        date_type: DateType {
+            value: record.date_type,
+            display_name: Some(record.display_name),
+        },
+        count: record.count,
+    })
+    .collect())
+}
+
+#[get("/admin/result-mail")]
+pub async fn result_mail_get(
+    lang: Language,
+    csrf: CsrfToken,
+    admin: Authorized<SendResultMails>,
+    mut db: Connection<Database>,
+) -> RocketResult<Template> {
+    let lang = lang.into_string();
+    let pending = get_pending_result_mails(&mut db, &admin, &lang).await?;
+    Ok(Template::render(
+        "result-mail",
+        context! { csrf_token: csrf.as_str(), lang, pending },
+    ))
+}
+
+#[derive(FromForm)]
+pub struct ResultMailForm<'r> {
+    date_type: &'r str,
+}
+
+#[post("/admin/result-mail", data = "<form>")]
+pub async fn result_mail_post(
+    lang: Language,
+    csrf: CsrfToken,
+    admin: Authorized<SendResultMails>,
+    mut db: Connection<Database>,
+    config: &State<Config>,
+    form: CsrfForm<ResultMailForm<'_>>,
+) -> RocketResult<Result<Template, Status>> {
+    let lang = lang.into_string();
+    let ResultMailForm { date_type } = form.into_inner();
+    if !admin.may_access(date_type) {
+        return Ok(Err(Status::Forbidden));
+    }
+
+    // Results are claimed before their mails are queued, so a result is only mailed once even if
+    // the form is submitted twice.
+    let mut tx = db.begin().await?;
+    let results = sqlx::query!(
+        r#"with claimed as (
+            update booking_results set mailed = now()
+            from bookings
+            join dates on dates.id = bookings.date_id
+            where bookings.token = booking_results.booking
+            and booking_results.mailed is null
+            and dates.date_type = $1
+            returning booking_results.booking, booking_results.outcome, booking_results.voice
+        )
+        select
+        claimed.booking,
+        claimed.outcome::text as "outcome!",
+        bookings.email,
+        bookings.person_name,
+        bookings.lang,
+        date_types_translations.display_name as date_type_display_name,
+        voices_translations.display_name as "voice?"
+        from claimed
+        join bookings on bookings.token = claimed.booking
+        join dates on dates.id = bookings.date_id
+        join date_types_translations
+            on date_types_translations.date_type = dates.date_type and date_types_translations.lang = bookings.lang
+        left join voices_translations
+            on voices_translations.voice = claimed.voice and voices_translations.lang = bookings.lang
+        order by dates.from_date"#,
+        &date_type
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut mailed = Vec::new();
+    for result in results {
+        let subject_args = hash_map! {
+            "datetype" => result.date_type_display_name.as_str()
+        };
+        send_mail(
+            &mut tx,
+            config,
+            &result.email,
+            &result.lang,
+            "mail-result-subject",
+            Some(&subject_args),
+            MailBody::Template(
+                &format!("result-{}.tera", &result.outcome),
+                &tera::Context::from_serialize(context! {
+                    lang: &result.lang,
+                    person_name: &result.person_name,
+                    date_type: &result.date_type_display_name,
+                    voice: &result.voice,
+                })?,
+            ),
+        )
+        .await?;
+        mailed.push(result.booking);
+    }
+    if !mailed.is_empty() {
+        audit::log(
+            &mut tx,
+            Some(&admin.id),
+            "result-mail",
+            &mailed,
+            json!({ "date_type": date_type }),
+        )
+        .await?;
+    }
+    tx.commit().await?;
+
+    let pending = get_pending_result_mails(&mut db, &admin, &lang).await?;
+    Ok(Ok(Template::render(
+        "result-mail",
+        context! {
+            csrf_token: csrf.as_str(),
+            lang,
+            pending,
+            messages: [Message {
+                text_key: String::from("result-mail-sent"),
+                message_type: MessageType::Success,
+            }],
+        },
+    )))
+}
+
+const AUDIT_LOG_PAGE_SIZE: i64 = 50;
+
+#[derive(Serialize)]
+pub struct AuditLogEntry {
+    created: DateTime<Utc>,
+    admin: Option<String>,
+    action: String,
+    targets: Vec<String>,
+    payload: String,
+}
+
+#[get("/admin/audit-log?<action>&<admin_id>&<target>&<page>")]
+pub async fn audit_log_get(
+    lang: Language,
+    _admin: Authorized<ViewAuditLog>,
+    mut db: Connection<Database>,
+    action: Option<&str>,
+    admin_id: Option<&str>,
+    target: Option<&str>,
+    page: Option<i64>,
+) -> RocketResult<Result<Template, Status>> {
+    let action = action.filter(|action| !action.is_empty());
+    let admin_id = admin_id.filter(|admin_id| !admin_id.is_empty());
+    let target = target.filter(|target| !target.is_empty());
+    let page = page.unwrap_or(0).max(0);
+    let offset = match page.checked_mul(AUDIT_LOG_PAGE_SIZE) {
+        Some(offset) => offset,
+        None => return Ok(Err(Status::BadRequest)),
+    };
+
+    // One more entry than shown is loaded to find out whether there is a next page.
+    let mut entries = sqlx::query!(
+        r#"select
+        audit_log.created,
+        admins.display_name as "admin?",
+        audit_log.action,
+        audit_log.targets,
+        audit_log.payload
+        from audit_log
+        left join admins on admins.id = audit_log.admin_id
+        where ($1::text is null or audit_log.action = $1)
+        and ($2::text is null or audit_log.admin_id = $2)
+        and ($3::text is null or $3 = any(audit_log.targets))
+        order by audit_log.created desc, audit_log.id desc
+        limit $4 offset $5"#,
+        action,
+        admin_id,
+        target,
+        AUDIT_LOG_PAGE_SIZE + 1,
+        offset,
+    )
+    .fetch_all(&mut *db)
+    .await?
+    .into_iter()
+    .map(|record| {
+        Ok(AuditLogEntry {
+            created: record.created,
+            admin: record.admin,
+            action: record.action,
+            targets: record.targets,
+            payload: serde_json::to_string_pretty(&record.payload)?,
+        })
+    })
+    .collect::<anyhow::Result<Vec<_>>>()?;
+    let has_next = entries.len() as i64 > AUDIT_LOG_PAGE_SIZE;
+    entries.truncate(AUDIT_LOG_PAGE_SIZE as usize);
+
+    let actions = sqlx::query_scalar!("select distinct action from audit_log order by action")
+        .fetch_all(&mut *db)
+        .await?;
+    let admins = sqlx::query!("select id, display_name from admins order by display_name")
+        .fetch_all(&mut *db)
+        .await?
+        .into_iter()
+        .map(|record| hash_map! { "id" => record.id, "display_name" => record.display_name })
+        .collect::<Vec<_>>();
+
+    Ok(Ok(Template::render(
+        "audit-log",
+        context! {
+            lang: lang.into_string(),
+            entries,
+            actions,
+            admins,
+            action: action.unwrap_or_default(),
+            admin_id: admin_id.unwrap_or_default(),
+            target: target.unwrap_or_default(),
+            page,
+            has_next,
+        },
+    )))
+}
+
+#[derive(Serialize)]
+pub struct OutboxEntry {
+    id: i64,
+    recipient: String,
+    subject: String,
+    created: DateTime<Utc>,
+    attempts: i32,
+    next_attempt: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+}
+
+async fn render_outbox(
+    lang: String,
+    csrf: CsrfToken,
+    db: &mut Connection<Database>,
+    messages: Vec<Message>,
+) -> anyhow::Result<Template> {
+    // Mails which have been given up come first, as they need attention.
+    let mails = sqlx::query_as!(
+        OutboxEntry,
+        "select id, recipient, subject, created, attempts, next_attempt, last_error \
+        from mail_outbox \
+        order by next_attempt nulls first, id"
+    )
+    .fetch_all(&mut **db)
+    .await?;
+    Ok(Template::render(
+        "outbox",
+        context! {
+            csrf_token: csrf.as_str(),
+            lang,
+            mails,
+            messages,
+        },
+    ))
+}
+
+#[get("/admin/outbox")]
+pub async fn outbox_get(
+    lang: Language,
+    csrf: CsrfToken,
+    _admin: Authorized<ManageMails>,
+    mut db: Connection<Database>,
+) -> RocketResult<Template> {
+    Ok(render_outbox(lang.into_string(), csrf, &mut db, Vec::new()).await?)
+}
+
+#[derive(FromForm)]
+pub struct OutboxForm<'r> {
+    button: &'r str,
+}
+
+#[post("/admin/outbox", data = "<form>")]
+pub async fn outbox_post(
+    lang: Language,
+    csrf: CsrfToken,
+    admin: Authorized<ManageMails>,
+    mut db: Connection<Database>,
+    form: CsrfForm<OutboxForm<'_>>,
+) -> RocketResult<Result<Template, Status>> {
+    let mut messages = Vec::new();
+    if let Some(id) = form.button.strip_prefix("delete-") {
+        let id: i64 = match id.parse() {
+            Ok(id) => id,
+            Err(_) => return Ok(Err(Status::BadRequest)),
+        };
+        // Mails which are reserved by a delivery right now can't be deleted, as they are sent anyway.
+        let recipient = sqlx::query_scalar!(
+            "delete from mail_outbox \
+            where id = $1 and (next_attempt is null or next_attempt <= now()) \
+            returning recipient",
+            id
+        )
+        .fetch_optional(&mut *db)
+        .await?;
+        if let Some(recipient) = recipient {
+            audit::log(
+                &mut db,
+                Some(&admin.id),
+                "mail-delete",
+                &[id.to_string()],
+                json!({ "recipient": recipient }),
+            )
+            .await?;
+            messages.push(Message {
+                text_key: String::from("outbox-deleted"),
+                message_type: MessageType::Success,
+            });
+        }
+    } else {
+        // Either a single mail or all mails which have been given up are retried at the next
+        // delivery. Mails which are reserved by a delivery right now are left alone, as they would
+        // be sent twice.
+        let id = match form.button.strip_prefix("retry-") {
+            Some("failed") => None,
+            Some(id) => match id.parse::<i64>() {
+                Ok(id) => Some(id),
+                Err(_) => return Ok(Err(Status::BadRequest)),
+            },
+            None => return Ok(Err(Status::BadRequest)),
+        };
+        let mut tx = db.begin().await?;
+        let retried = sqlx::query_scalar!(
+            "update mail_outbox set next_attempt = now(), attempts = 0 \
+            where ($1::bigint is null and next_attempt is null) \
+            or (id = $1 and (next_attempt is null or next_attempt <= now())) \
+            returning id",
+            id
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        if !retried.is_empty() {
+            audit::log(
+                &mut tx,
+                Some(&admin.id),
+                "mail-retry",
+                &retried.iter().map(i64::to_string).collect::<Vec<_>>(),
+                json!({}),
+            )
+            .await?;
+            messages.push(Message {
+                text_key: String::from("outbox-retried"),
+                message_type: MessageType::Success,
+            });
+        }
+        tx.commit().await?;
+    }
+    Ok(Ok(render_outbox(
+        lang.into_string(),
+        csrf,
+        &mut db,
+        messages,
+    )
+    .await?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(value: &str) -> Option<String> {
+        parse_import_datetime(value).map(|datetime| datetime.format("%Y-%m-%d %H:%M").to_string())
+    }
+
+    #[test]
+    fn parses_import_datetimes() {
+        let expected = Some(String::from("2031-03-16 10:05"));
+        assert_eq!(parse("2031-03-16 10:05"), expected);
+        assert_eq!(parse("2031-03-16T10:05"), expected);
+        assert_eq!(parse("16.03.2031 10:05"), expected);
+        assert_eq!(parse(" 16.03.2031 10:05\r"), expected);
+    }
+
+    #[test]
+    fn rejects_bad_import_datetimes() {
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("2031-03-16"), None);
+        assert_eq!(parse("10:05"), None);
+        assert_eq!(parse("2031-02-30 10:00"), None);
+        assert_eq!(parse("2031-13-01 10:00"), None);
+        assert_eq!(parse("2031-03-16 24:00"), None);
+        assert_eq!(parse("2031-03-16 10:60"), None);
+        assert_eq!(parse("16/03/2031 10:05"), None);
+        assert_eq!(parse("03.16.2031 10:05"), None);
+        assert_eq!(parse("2031-03-16 10:05 Uhr"), None);
+    }
 }
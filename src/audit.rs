@@ -0,0 +1,25 @@
+use rocket_db_pools::sqlx::{self, PgConnection};
+use serde_json::Value;
+
+/// Appends an entry to the audit log.
+///
+/// `admin_id` is `None` if the action was not performed by a logged-in admin, e.g. a booking by a
+/// candidate. `targets` are the ids of the affected objects, `payload` holds any further details.
+pub async fn log(
+    db: &mut PgConnection,
+    admin_id: Option<&str>,
+    action: &str,
+    targets: &[String],
+    payload: Value,
+) -> anyhow::Result<()> {
+    sqlx::query!(
+        "insert into audit_log (admin_id, action, targets, payload) values ($1, $2, $3, $4)",
+        admin_id,
+        action,
+        targets,
+        payload,
+    )
+    .execute(db)
+    .await?;
+    Ok(())
+}
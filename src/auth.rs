@@ -1,3 +1,7 @@
+use std::marker::PhantomData;
+use std::net::IpAddr;
+use std::ops::Deref;
+
 use anyhow::anyhow;
 use anyhow::Context as AnyhowContext;
 use argon2::password_hash::SaltString;
@@ -7,13 +11,7 @@ use argon2::PasswordHasher;
 use argon2::PasswordVerifier;
 use chrono::DateTime;
 use chrono::{Duration, Utc};
-use lettre::message::header;
-use lettre::message::header::ContentTransferEncoding;
-use lettre::message::IntoBody;
-use lettre::AsyncTransport;
-use lettre::Message as EmailMessage;
 use rand_core::OsRng;
-use rocket::form::Form;
 use rocket::form::Result as FormResult;
 use rocket::http::Cookie;
 use rocket::http::CookieJar;
@@ -24,49 +22,64 @@ use rocket::response::Redirect;
 use rocket::Request;
 use rocket::State;
 use rocket_db_pools::sqlx;
+use rocket_db_pools::sqlx::{Acquire, PgConnection};
 use rocket_db_pools::Connection;
 use rocket_dyn_templates::{context, Template};
 use serde::Deserialize;
 use serde::Serialize;
+use serde_json::json;
 use tera::Context;
 
+use crate::audit;
 use crate::Config;
 use crate::Database;
-use crate::Mailer;
 use crate::RocketResult;
-use crate::MAIL_TEMPLATES;
 
+use crate::auth::require::{ManageAdmins, RequiredPermission};
+use crate::csrf::{CsrfForm, CsrfToken};
 use crate::mail::send_mail;
 use crate::mail::MailBody;
 use crate::model::handle_form_error;
+use crate::model::DateType;
 use crate::model::DisplayName;
 use crate::model::Email;
 use crate::model::Password;
+use crate::rate_limit::{self, Action as RateLimitAction, Key};
 use crate::{
-    language::{Language, LOCALES},
+    language::Language,
     model::{Message, MessageType},
 };
 
 #[derive(FromForm)]
 pub struct InviteForm<'r> {
     email: FormResult<'r, Email<'r>>,
+    role: Option<Role>,
+    date_types: Vec<&'r str>,
 }
 
 #[get("/admin/invite")]
 pub async fn invite_get(
     lang: Language,
-    db: Connection<Database>,
+    csrf: CsrfToken,
+    mut db: Connection<Database>,
     admin: Option<Admin>,
 ) -> RocketResult<Result<Template, Status>> {
+    let lang = lang.into_string();
     Ok(match admin {
-        Some(_) => Ok(Template::render(
-            "invite",
-            context! { lang: lang.into_string() },
-        )),
-        None if no_one_registered(db).await? => Ok(Template::render(
+        Some(admin) if admin.can(Permission::ManageAdmins) => {
+            let date_types = admin.accessible_date_types(&mut db, &lang).await?;
+            Ok(Template::render(
+                "invite",
+                context! { csrf_token: csrf.as_str(), lang, roles: Role::ALL, date_types },
+            ))
+        }
+        Some(_) => Err(Status::Forbidden),
+        None if no_one_registered(&mut db).await? => Ok(Template::render(
             "invite",
             context! {
-                lang: lang.into_string(),
+                csrf_token: csrf.as_str(),
+                lang,
+                initial: true,
                 messages: [Message { text_key: String::from("initial-registration-info"), message_type: MessageType::Info }],
             },
         )),
@@ -74,33 +87,103 @@ pub async fn invite_get(
     })
 }
 
-async fn no_one_registered(mut db: Connection<Database>) -> anyhow::Result<bool> {
+async fn send_invite_mail(
+    db: &mut PgConnection,
+    config: &Config,
+    lang: &str,
+    email: &str,
+    token: &str,
+) -> anyhow::Result<()> {
+    let link = format!("{}/admin/register?token={}", &config.web_address, &token);
+    let mut mail_context = tera::Context::new();
+    mail_context.insert("lang", &lang);
+    mail_context.insert("link", &link);
+    send_mail(
+        db,
+        config,
+        email,
+        lang,
+        "mail-invite-subject",
+        None,
+        MailBody::Template("invite.tera", &mail_context),
+    )
+    .await
+    .context("Could not send invitation mail!")
+}
+
+async fn no_one_registered(db: &mut Connection<Database>) -> anyhow::Result<bool> {
     Ok(sqlx::query!("select id from admins")
-        .fetch_optional(&mut *db)
+        .fetch_optional(&mut **db)
         .await?
         .is_none())
 }
 
 #[post("/admin/invite", data = "<invite>")]
+#[allow(clippy::too_many_arguments)]
 pub async fn invite_post<'r>(
     lang: Language,
+    csrf: CsrfToken,
     mut db: Connection<Database>,
-    mailer: &State<Mailer>,
+    admin: Option<Admin>,
     config: &State<Config>,
-    invite: Form<InviteForm<'r>>,
-) -> RocketResult<Template> {
+    ip: Option<IpAddr>,
+    invite: CsrfForm<InviteForm<'r>>,
+) -> RocketResult<Result<Template, Status>> {
     let lang = lang.into_string();
-    let InviteForm { email } = invite.into_inner();
+    let InviteForm {
+        email,
+        role,
+        date_types,
+    } = invite.into_inner();
+
+    let actor = admin.as_ref().map(|admin| admin.id.clone());
+    // The very first admin is always an owner without restrictions.
+    let (role, date_types, available_date_types) = match admin {
+        Some(admin) if admin.can(Permission::ManageAdmins) => {
+            let available_date_types = admin.accessible_date_types(&mut db, &lang).await?;
+            let date_types: Vec<String> = match (&admin.date_types, date_types.is_empty()) {
+                (Some(restriction), true) => restriction.clone(),
+                _ => date_types.into_iter().map(String::from).collect(),
+            };
+            (
+                role.unwrap_or(Role::Viewer),
+                date_types,
+                Some(available_date_types),
+            )
+        }
+        Some(_) => return Ok(Err(Status::Forbidden)),
+        None if no_one_registered(&mut db).await? => (Role::Owner, Vec::new(), None),
+        None => return Ok(Err(Status::Unauthorized)),
+    };
+    let initial = available_date_types.is_none();
+
     let mut messages = Vec::new();
     let email = handle_form_error(email, &mut messages);
+    if let Some(available_date_types) = &available_date_types {
+        let valid = date_types.iter().all(|date_type| {
+            available_date_types
+                .iter()
+                .any(|available| &available.value == date_type)
+        });
+        if !valid {
+            messages.push(Message {
+                text_key: String::from("validation-select"),
+                message_type: MessageType::Error,
+            });
+        }
+    }
     if !messages.is_empty() {
-        return Ok(Template::render(
+        return Ok(Ok(Template::render(
             "invite",
             context! {
+                csrf_token: csrf.as_str(),
                 lang,
+                initial,
+                roles: Role::ALL,
+                date_types: available_date_types,
                 messages,
             },
-        ));
+        )));
     }
 
     if sqlx::query!("select email from admins where email = $1", &email)
@@ -108,74 +191,95 @@ pub async fn invite_post<'r>(
         .await?
         .is_some()
     {
-        return Ok(Template::render(
+        return Ok(Ok(Template::render(
             "invite",
             context! {
+                csrf_token: csrf.as_str(),
                 lang,
+                initial,
+                roles: Role::ALL,
+                date_types: available_date_types,
                 messages: [Message {
                     text_key: String::from("email-already-registered"),
                     message_type: MessageType::Error
                 }],
             },
-        ));
+        )));
+    }
+
+    let keys = rate_limit::keys(ip, email);
+    if !rate_limit::attempt(&mut db, RateLimitAction::Invite, &keys).await? {
+        return Ok(Ok(Template::render(
+            "invite",
+            context! {
+                csrf_token: csrf.as_str(),
+                lang,
+                initial,
+                roles: Role::ALL,
+                date_types: available_date_types,
+                messages: [Message {
+                    text_key: String::from("rate-limited"),
+                    message_type: MessageType::Error
+                }],
+            },
+        )));
     }
 
+    let mut tx = db.begin().await?;
     let token = match sqlx::query!("select token from invites where email = $1", &email)
-        .fetch_optional(&mut *db)
+        .fetch_optional(&mut *tx)
         .await?
     {
         Some(record) => {
             sqlx::query!(
-                "update invites set created = now() where email = $1",
-                &email
+                "update invites set created = now(), role = $2, date_types = $3 where email = $1",
+                &email,
+                role as Role,
+                &date_types,
             )
-            .execute(&mut *db)
+            .execute(&mut *tx)
             .await?;
             record.token
         }
         None => {
             sqlx::query!(
-                "insert into invites (token, email, created) values (DEFAULT, $1, now()) returning token",
+                "insert into invites (token, email, created, role, date_types) \
+                values (DEFAULT, $1, now(), $2, $3) returning token",
                 &email,
+                role as Role,
+                &date_types,
             )
-            .fetch_one(&mut *db)
-            .await?.token
+            .fetch_one(&mut *tx)
+            .await?
+            .token
         }
     };
 
-    let link = format!("{}/admin/register?token={}", &config.web_address, &token);
-    let mut mail_context = tera::Context::new();
-    mail_context.insert("lang", &lang);
-    mail_context.insert("link", &link);
-    let mail = EmailMessage::builder()
-        .to(email.parse()?)
-        .from(config.email_from_address.parse()?)
-        .subject(
-            LOCALES
-                .lookup_single_language::<&str>(&lang.parse()?, "mail-invite-subject", None)
-                .ok_or_else(|| anyhow!("Missing translation for mail-invite-subject!"))?,
-        )
-        .header(header::ContentType::TEXT_PLAIN)
-        .body(
-            MAIL_TEMPLATES
-                .render("invite.tera", &mail_context)?
-                .into_body(Some(ContentTransferEncoding::Base64)),
-        )?;
-    mailer
-        .send(mail)
-        .await
-        .context("Could not send invitation mail!")?;
+    send_invite_mail(&mut tx, config, &lang, email, &token).await?;
+    audit::log(
+        &mut tx,
+        actor.as_deref(),
+        "invite",
+        &[String::from(email)],
+        json!({ "role": role, "date_types": date_types }),
+    )
+    .await?;
+    tx.commit().await?;
 
-    Ok(Template::render(
+    Ok(Ok(Template::render(
         "invite",
         context! {
+            csrf_token: csrf.as_str(),
             lang,
+            initial,
+            roles: Role::ALL,
+            date_types: available_date_types,
             messages: [Message {
                 text_key: String::from("invite-successful"),
                 message_type: MessageType::Success
             }],
         },
-    ))
+    )))
 }
 
 #[derive(FromForm)]
@@ -189,17 +293,33 @@ pub struct RegisterForm<'r> {
 #[get("/admin/register?<token>")]
 pub async fn register_get(
     lang: Language,
+    csrf: CsrfToken,
     mut db: Connection<Database>,
+    config: &State<Config>,
     token: &str,
 ) -> RocketResult<Result<Template, Status>> {
-    match sqlx::query!("select email from invites where token = $1", &token)
-        .fetch_optional(&mut *db)
-        .await?
+    match sqlx::query!(
+        r#"select email, created < now() - make_interval(hours => $2) as "expired!"
+        from invites where token = $1"#,
+        &token,
+        config.invite_lifetime_hours,
+    )
+    .fetch_optional(&mut *db)
+    .await?
     {
         None => Ok(Err(Status::Unauthorized)),
+        Some(record) if record.expired => {
+            Ok(Ok(link_expired(lang.into_string(), "invite-expired")))
+        }
         Some(record) => Ok(Ok(Template::render(
             "register",
-            context! { lang: lang.into_string(), token, email: record.email, display_name: "" },
+            context! {
+                csrf_token: csrf.as_str(),
+                lang: lang.into_string(),
+                token,
+                email: record.email,
+                display_name: "",
+            },
         ))),
     }
 }
@@ -207,8 +327,10 @@ pub async fn register_get(
 #[post("/admin/register", data = "<form>")]
 pub async fn register_post(
     lang: Language,
+    csrf: CsrfToken,
     mut db: Connection<Database>,
-    form: Form<RegisterForm<'_>>,
+    config: &State<Config>,
+    form: CsrfForm<RegisterForm<'_>>,
 ) -> RocketResult<Result<Redirect, Template>> {
     let RegisterForm {
         email,
@@ -224,6 +346,7 @@ pub async fn register_post(
         return Ok(Err(Template::render(
             "register",
             context! {
+                csrf_token: csrf.as_str(),
                 lang: lang.into_string(),
                 token,
                 email,
@@ -233,38 +356,72 @@ pub async fn register_post(
         )));
     }
 
-    let rows_affected = sqlx::query!("delete from invites where token = $1", &token)
-        .execute(&mut *db)
-        .await?
-        .rows_affected();
-    if rows_affected == 0 {
-        return Ok(Err(Template::render(
-            "register",
-            context! {
-                lang: lang.into_string(),
-                token,
-                email,
-                display_name,
-                messages: [Message { text_key: String::from("invite-invalid-token"), message_type: MessageType::Error }],
-            },
-        )));
-    }
+    // The admin is only created together with their date types, as an admin without date types
+    // may access all of them.
+    let mut tx = db.begin().await?;
+    let invite = sqlx::query!(
+        r#"delete from invites where token = $1
+        returning
+            role as "role: Role",
+            date_types,
+            created < now() - make_interval(hours => $2) as "expired!""#,
+        &token,
+        config.invite_lifetime_hours,
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+    let invite = match invite {
+        Some(invite) if invite.expired => {
+            return Ok(Err(link_expired(lang.into_string(), "invite-expired")))
+        }
+        Some(invite) => invite,
+        None => {
+            return Ok(Err(Template::render(
+                "register",
+                context! {
+                    csrf_token: csrf.as_str(),
+                    lang: lang.into_string(),
+                    token,
+                    email,
+                    display_name,
+                    messages: [Message { text_key: String::from("invite-invalid-token"), message_type: MessageType::Error }],
+                },
+            )));
+        }
+    };
 
-    sqlx::query!(
-        "insert into admins (display_name, email, password) values ($1, $2, $3)",
+    let id = sqlx::query_scalar!(
+        "insert into admins (display_name, email, password, role) values ($1, $2, $3, $4) returning id",
         &display_name,
         &email,
-        hash_password(&password)?
+        hash_password(&password)?,
+        invite.role as Role,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    sqlx::query!(
+        "insert into admins_date_types (admin_id, date_type) select $1, unnest($2::text[])",
+        &id,
+        &invite.date_types,
+    )
+    .execute(&mut *tx)
+    .await?;
+    audit::log(
+        &mut tx,
+        Some(&id),
+        "register",
+        std::slice::from_ref(&id),
+        json!({ "email": email, "display_name": display_name, "role": invite.role }),
     )
-    .execute(&mut *db)
     .await?;
+    tx.commit().await?;
 
     Ok(Ok(Redirect::to(uri!(login_get(
         redirect = Option::<&str>::None
     )))))
 }
 
-fn hash_password(password: &str) -> anyhow::Result<String> {
+pub fn hash_password(password: &str) -> anyhow::Result<String> {
     let salt = SaltString::generate(&mut OsRng);
     let argon2 = Argon2::default();
     let password_hash = argon2
@@ -273,7 +430,7 @@ fn hash_password(password: &str) -> anyhow::Result<String> {
     Ok(password_hash.to_string())
 }
 
-fn verify_password(password: &str, hash: &str) -> anyhow::Result<bool> {
+pub fn verify_password(password: &str, hash: &str) -> anyhow::Result<bool> {
     let parsed_hash =
         PasswordHash::new(hash).map_err(|_| anyhow!("Could not parse password hash!"))?;
     Ok(Argon2::default()
@@ -290,23 +447,100 @@ pub struct LoginForm<'r> {
 
 #[derive(Serialize, Deserialize)]
 struct LoginCookie {
-    id: String,
-    valid_until: DateTime<Utc>,
+    session: String,
+}
+
+pub const PENDING_LOGIN_COOKIE: &str = "login-pending";
+
+/// A login whose password has been verified, but which still waits for the second factor.
+#[derive(Serialize, Deserialize)]
+pub struct PendingLogin {
+    pub admin: String,
+    pub remember: bool,
+    pub valid_until: DateTime<Utc>,
+}
+
+/// Creates a new session for the admin and sets the login cookie.
+pub async fn start_session(
+    db: &mut Connection<Database>,
+    cookies: &CookieJar<'_>,
+    admin_id: &str,
+    remember: bool,
+    user_agent: &UserAgent,
+) -> anyhow::Result<()> {
+    sqlx::query!(
+        "update admins set last_login = now() where id = $1",
+        admin_id
+    )
+    .execute(&mut **db)
+    .await?;
+    sqlx::query!("delete from sessions where valid_until <= now()")
+        .execute(&mut **db)
+        .await?;
+    let session = sqlx::query_scalar!(
+        "insert into sessions (admin_id, valid_until, user_agent) values ($1, $2, $3) returning id",
+        admin_id,
+        Utc::now() + Duration::days(7),
+        &user_agent.0,
+    )
+    .fetch_one(&mut **db)
+    .await?;
+    audit::log(
+        db,
+        Some(admin_id),
+        "login",
+        std::slice::from_ref(&session),
+        json!({ "user_agent": user_agent.0 }),
+    )
+    .await?;
+    let cookie_value = serde_json::to_string(&LoginCookie { session })?;
+    let cookie = if remember {
+        Cookie::build("login", cookie_value)
+            .http_only(true)
+            .finish()
+    } else {
+        Cookie::build("login", cookie_value)
+            .http_only(true)
+            .expires(None)
+            .finish()
+    };
+    cookies.add_private(cookie);
+    Ok(())
+}
+
+/// The `User-Agent` header, stored with a session to help admins recognize their devices.
+pub struct UserAgent(String);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for UserAgent {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let user_agent = req.headers().get_one("User-Agent").unwrap_or_default();
+        request::Outcome::Success(UserAgent(String::from(user_agent)))
+    }
 }
 
 #[get("/admin/login?<redirect>")]
 #[allow(unused_variables)]
-pub async fn login_get(lang: Language, redirect: Option<&str>) -> Template {
-    Template::render("login", context! { lang: lang.into_string(), email: "" })
+pub async fn login_get(lang: Language, csrf: CsrfToken, redirect: Option<&str>) -> Template {
+    Template::render(
+        "login",
+        context! { csrf_token: csrf.as_str(), lang: lang.into_string(), email: "" },
+    )
 }
 
 #[post("/admin/login?<redirect>", data = "<form>")]
+#[allow(clippy::too_many_arguments)]
 pub async fn login_post<'r>(
     lang: Language,
+    csrf: CsrfToken,
     mut db: Connection<Database>,
     cookies: &CookieJar<'_>,
+    user_agent: UserAgent,
+    ip: Option<IpAddr>,
     redirect: Option<&'r str>,
-    form: Form<LoginForm<'r>>,
+    form: CsrfForm<LoginForm<'r>>,
 ) -> RocketResult<Result<Redirect, Template>> {
     let LoginForm {
         email,
@@ -319,37 +553,54 @@ pub async fn login_post<'r>(
     if !messages.is_empty() {
         return Ok(Err(Template::render(
             "login",
-            context! { lang: lang.into_string(), email },
+            context! { csrf_token: csrf.as_str(), lang: lang.into_string(), email },
         )));
     }
 
-    match sqlx::query!("select id, password from admins where email = $1", &email)
-        .fetch_optional(&mut *db)
-        .await?
+    // The attempt is counted before the password is verified, so parallel requests can't get
+    // around the limit. Successful logins clear the attempts of the account again.
+    let keys = rate_limit::keys(ip, email);
+    if !rate_limit::attempt(&mut db, RateLimitAction::Login, &keys).await? {
+        return Ok(Err(Template::render(
+            "login",
+            context! {
+                csrf_token: csrf.as_str(),
+                lang: lang.into_string(),
+                email,
+                messages: [Message {
+                    text_key: String::from("rate-limited"),
+                    message_type: MessageType::Error,
+                }],
+            },
+        )));
+    }
+
+    match sqlx::query!(
+        "select id, password, totp_enabled from admins where email = $1",
+        &email
+    )
+    .fetch_optional(&mut *db)
+    .await?
     {
         Some(record) if verify_password(password, &record.password)? => {
-            sqlx::query!(
-                "update admins set last_login = now() where id = $1",
-                &record.id
-            )
-            .execute(&mut *db)
-            .await?;
-            let valid_until = Utc::now() + Duration::days(7);
-            let cookie_value = serde_json::to_string(&LoginCookie {
-                id: record.id,
-                valid_until,
-            })?;
-            let cookie = if remember {
-                Cookie::build("login", cookie_value)
-                    .http_only(true)
-                    .finish()
-            } else {
-                Cookie::build("login", cookie_value)
-                    .http_only(true)
-                    .expires(None)
-                    .finish()
-            };
-            cookies.add_private(cookie);
+            rate_limit::clear(&mut db, RateLimitAction::Login, &Key::Account(email)).await?;
+            if record.totp_enabled {
+                let pending = serde_json::to_string(&PendingLogin {
+                    admin: record.id,
+                    remember,
+                    valid_until: Utc::now() + Duration::minutes(10),
+                })?;
+                cookies.add_private(
+                    Cookie::build(PENDING_LOGIN_COOKIE, pending)
+                        .http_only(true)
+                        .expires(None)
+                        .finish(),
+                );
+                return Ok(Ok(Redirect::to(uri!(
+                    crate::two_factor::login_two_factor_get(redirect.map(String::from))
+                ))));
+            }
+            start_session(&mut db, cookies, &record.id, remember, &user_agent).await?;
             let redirect = match redirect {
                 None => Redirect::to(uri!(crate::admin::dashboard(day = Option::<&str>::None))),
                 Some(redirect) => Redirect::to(String::from(redirect)),
@@ -359,6 +610,7 @@ pub async fn login_post<'r>(
         _ => Ok(Err(Template::render(
             "login",
             context! {
+                csrf_token: csrf.as_str(),
                 lang: lang.into_string(),
                 email,
                 messages: [Message {
@@ -371,9 +623,642 @@ pub async fn login_post<'r>(
 }
 
 #[get("/admin/logout")]
-pub async fn logout(cookies: &CookieJar<'_>) -> Redirect {
+pub async fn logout(
+    mut db: Connection<Database>,
+    cookies: &CookieJar<'_>,
+) -> RocketResult<Redirect> {
+    if let Some(login) = cookies
+        .get_private("login")
+        .and_then(|cookie| serde_json::from_str::<LoginCookie>(cookie.value()).ok())
+    {
+        let admin_id = sqlx::query_scalar!(
+            "delete from sessions where id = $1 returning admin_id",
+            &login.session
+        )
+        .fetch_optional(&mut *db)
+        .await?;
+        audit::log(
+            &mut db,
+            admin_id.as_deref(),
+            "logout",
+            &[login.session],
+            json!({}),
+        )
+        .await?;
+    }
     cookies.remove_private(Cookie::new("login", ""));
-    Redirect::to(uri!("/"))
+    Ok(Redirect::to(uri!("/")))
+}
+
+#[derive(Serialize)]
+struct Session {
+    id: String,
+    created: DateTime<Utc>,
+    last_seen: DateTime<Utc>,
+    user_agent: String,
+    current: bool,
+}
+
+async fn get_sessions(
+    db: &mut Connection<Database>,
+    admin: &Admin,
+) -> anyhow::Result<Vec<Session>> {
+    Ok(sqlx::query_as!(
+        Session,
+        r#"select id, created, last_seen, user_agent, id = $2 as "current!"
+        from sessions
+        where admin_id = $1 and valid_until > now()
+        order by last_seen desc"#,
+        &admin.id,
+        &admin.session,
+    )
+    .fetch_all(&mut **db)
+    .await?)
+}
+
+#[get("/admin/sessions")]
+pub async fn sessions_get(
+    lang: Language,
+    csrf: CsrfToken,
+    mut db: Connection<Database>,
+    admin: Admin,
+) -> RocketResult<Template> {
+    let sessions = get_sessions(&mut db, &admin).await?;
+    Ok(Template::render(
+        "sessions",
+        context! { csrf_token: csrf.as_str(), lang: lang.into_string(), sessions },
+    ))
+}
+
+#[derive(FromForm)]
+pub struct SessionsForm<'r> {
+    button: &'r str,
+}
+
+#[post("/admin/sessions", data = "<form>")]
+pub async fn sessions_post(
+    lang: Language,
+    csrf: CsrfToken,
+    mut db: Connection<Database>,
+    cookies: &CookieJar<'_>,
+    admin: Admin,
+    form: CsrfForm<SessionsForm<'_>>,
+) -> RocketResult<Result<Template, Redirect>> {
+    let mut messages = Vec::new();
+    if form.button == "revoke-all" {
+        let sessions = sqlx::query_scalar!(
+            "delete from sessions where admin_id = $1 returning id",
+            &admin.id
+        )
+        .fetch_all(&mut *db)
+        .await?;
+        audit::log(
+            &mut db,
+            Some(&admin.id),
+            "session-revoke",
+            &sessions,
+            json!({}),
+        )
+        .await?;
+        cookies.remove_private(Cookie::new("login", ""));
+        return Ok(Err(Redirect::to(uri!(login_get(
+            redirect = Option::<&str>::None
+        )))));
+    } else if let Some(id) = form.button.strip_prefix("revoke-") {
+        sqlx::query!(
+            "delete from sessions where id = $1 and admin_id = $2",
+            id,
+            &admin.id
+        )
+        .execute(&mut *db)
+        .await?;
+        audit::log(
+            &mut db,
+            Some(&admin.id),
+            "session-revoke",
+            &[String::from(id)],
+            json!({}),
+        )
+        .await?;
+        if id == admin.session {
+            cookies.remove_private(Cookie::new("login", ""));
+            return Ok(Err(Redirect::to(uri!(login_get(
+                redirect = Option::<&str>::None
+            )))));
+        }
+        messages.push(Message {
+            text_key: String::from("session-revoked"),
+            message_type: MessageType::Success,
+        });
+    } else {
+        messages.push(Message {
+            text_key: String::from("validation-unknown"),
+            message_type: MessageType::Error,
+        });
+    }
+
+    let sessions = get_sessions(&mut db, &admin).await?;
+    Ok(Ok(Template::render(
+        "sessions",
+        context! { csrf_token: csrf.as_str(), lang: lang.into_string(), sessions, messages },
+    )))
+}
+
+#[derive(Serialize)]
+struct AdminEntry {
+    id: String,
+    display_name: String,
+    email: String,
+    role: Role,
+    date_types: Vec<String>,
+    last_login: Option<DateTime<Utc>>,
+    two_factor: bool,
+}
+
+#[derive(Serialize)]
+struct InviteEntry {
+    token: String,
+    email: String,
+    role: Option<Role>,
+    date_types: Option<Vec<String>>,
+    created: DateTime<Utc>,
+    expired: bool,
+}
+
+async fn render_admins(
+    lang: String,
+    csrf: CsrfToken,
+    db: &mut Connection<Database>,
+    config: &Config,
+    admin: &Admin,
+    messages: Vec<Message>,
+) -> anyhow::Result<Template> {
+    let admins = sqlx::query_as!(
+        AdminEntry,
+        r#"select
+        admins.id,
+        admins.display_name,
+        admins.email,
+        admins.role as "role: Role",
+        array(
+            select date_type from admins_date_types
+            where admin_id = admins.id order by date_type
+        ) as "date_types!",
+        admins.last_login,
+        admins.totp_enabled as two_factor
+        from admins
+        order by admins.display_name"#
+    )
+    .fetch_all(&mut **db)
+    .await?;
+    let invites = sqlx::query_as!(
+        InviteEntry,
+        r#"select
+        token,
+        email,
+        role as "role?: Role",
+        date_types as "date_types?",
+        created,
+        created < now() - make_interval(hours => $1) as "expired!"
+        from invites
+        order by created desc"#,
+        config.invite_lifetime_hours
+    )
+    .fetch_all(&mut **db)
+    .await?;
+    Ok(Template::render(
+        "admins",
+        context! {
+            csrf_token: csrf.as_str(),
+            lang,
+            admins,
+            invites,
+            current_admin: &admin.id,
+            messages,
+        },
+    ))
+}
+
+#[get("/admin/admins")]
+pub async fn admins_get(
+    lang: Language,
+    csrf: CsrfToken,
+    mut db: Connection<Database>,
+    config: &State<Config>,
+    admin: Authorized<ManageAdmins>,
+) -> RocketResult<Template> {
+    Ok(render_admins(
+        lang.into_string(),
+        csrf,
+        &mut db,
+        config,
+        &admin,
+        Vec::new(),
+    )
+    .await?)
+}
+
+#[derive(FromForm)]
+pub struct AdminsForm<'r> {
+    button: &'r str,
+}
+
+#[post("/admin/admins", data = "<form>")]
+pub async fn admins_post(
+    lang: Language,
+    csrf: CsrfToken,
+    mut db: Connection<Database>,
+    config: &State<Config>,
+    admin: Authorized<ManageAdmins>,
+    form: CsrfForm<AdminsForm<'_>>,
+) -> RocketResult<Result<Template, Status>> {
+    let lang = lang.into_string();
+    let mut messages = Vec::new();
+    if let Some(id) = form.button.strip_prefix("delete-") {
+        // All admins are locked, so concurrent deletes can't remove the last (owning) admin.
+        let mut tx = db.begin().await?;
+        let roles = sqlx::query_scalar!(r#"select role as "role: Role" from admins for update"#)
+            .fetch_all(&mut *tx)
+            .await?;
+        let target = match sqlx::query!(
+            r#"select
+            email,
+            display_name,
+            role as "role: Role",
+            array(select date_type from admins_date_types where admin_id = $1) as "date_types!"
+            from admins where id = $1"#,
+            id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        {
+            Some(target) => target,
+            None => return Ok(Err(Status::NotFound)),
+        };
+        if !admin.may_manage(&target.date_types) {
+            return Ok(Err(Status::Forbidden));
+        }
+        let owners = roles.iter().filter(|role| **role == Role::Owner).count();
+        if roles.len() <= 1 {
+            messages.push(Message {
+                text_key: String::from("admin-delete-last"),
+                message_type: MessageType::Error,
+            });
+        } else if target.role == Role::Owner && owners <= 1 {
+            messages.push(Message {
+                text_key: String::from("admin-delete-last-owner"),
+                message_type: MessageType::Error,
+            });
+        } else {
+            sqlx::query!("delete from password_resets where admin_id = $1", id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query!("delete from admins where id = $1", id)
+                .execute(&mut *tx)
+                .await?;
+            audit::log(
+                &mut tx,
+                Some(&admin.id),
+                "admin-delete",
+                &[String::from(id)],
+                json!({
+                    "email": target.email,
+                    "display_name": target.display_name,
+                    "role": target.role,
+                }),
+            )
+            .await?;
+            tx.commit().await?;
+            messages.push(Message {
+                text_key: String::from("admin-deleted"),
+                message_type: MessageType::Success,
+            });
+        }
+    } else if let Some(token) = form.button.strip_prefix("resend-") {
+        // Resending starts the lifetime of the invite anew.
+        let mut tx = db.begin().await?;
+        let invite = sqlx::query!(
+            "select email, date_types from invites where token = $1 for update",
+            token
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        if let Some(invite) = invite {
+            if !admin.may_manage(&invite.date_types) {
+                return Ok(Err(Status::Forbidden));
+            }
+            let email = invite.email;
+            sqlx::query!("update invites set created = now() where token = $1", token)
+                .execute(&mut *tx)
+                .await?;
+            send_invite_mail(&mut tx, config, &lang, &email, token).await?;
+            audit::log(
+                &mut tx,
+                Some(&admin.id),
+                "invite-resend",
+                &[email],
+                json!({}),
+            )
+            .await?;
+            tx.commit().await?;
+            messages.push(Message {
+                text_key: String::from("invite-resent"),
+                message_type: MessageType::Success,
+            });
+        }
+    } else if let Some(token) = form.button.strip_prefix("revoke-") {
+        let invite = sqlx::query!(
+            "select email, date_types from invites where token = $1",
+            token
+        )
+        .fetch_optional(&mut *db)
+        .await?;
+        if let Some(invite) = invite {
+            if !admin.may_manage(&invite.date_types) {
+                return Ok(Err(Status::Forbidden));
+            }
+            let email = invite.email;
+            sqlx::query!("delete from invites where token = $1", token)
+                .execute(&mut *db)
+                .await?;
+            audit::log(
+                &mut db,
+                Some(&admin.id),
+                "invite-revoke",
+                &[email],
+                json!({}),
+            )
+            .await?;
+            messages.push(Message {
+                text_key: String::from("invite-revoked"),
+                message_type: MessageType::Success,
+            });
+        }
+    } else {
+        messages.push(Message {
+            text_key: String::from("validation-unknown"),
+            message_type: MessageType::Error,
+        });
+    }
+    Ok(Ok(render_admins(
+        lang, csrf, &mut db, config, &admin, messages,
+    )
+    .await?))
+}
+
+async fn render_profile(
+    lang: String,
+    csrf: CsrfToken,
+    db: &mut Connection<Database>,
+    config: &Config,
+    admin: &Admin,
+    messages: Vec<Message>,
+) -> anyhow::Result<Template> {
+    let record = sqlx::query!(
+        "select display_name, email, calendar_token from admins where id = $1",
+        &admin.id
+    )
+    .fetch_one(&mut **db)
+    .await?;
+    // The feed of a single date type is linked by adding the date_type parameter.
+    let calendar_link = record.calendar_token.map(|token| {
+        format!(
+            "{}/calendar/{}/dates.ics?lang={}",
+            &config.web_address, token, &lang
+        )
+    });
+    let date_types = admin.accessible_date_types(db, &lang).await?;
+    Ok(Template::render(
+        "profile",
+        context! {
+            csrf_token: csrf.as_str(),
+            lang,
+            display_name: record.display_name,
+            email: record.email,
+            calendar_link,
+            date_types,
+            messages,
+        },
+    ))
+}
+
+#[get("/admin/profile")]
+pub async fn profile_get(
+    lang: Language,
+    csrf: CsrfToken,
+    mut db: Connection<Database>,
+    config: &State<Config>,
+    admin: Admin,
+) -> RocketResult<Template> {
+    Ok(render_profile(
+        lang.into_string(),
+        csrf,
+        &mut db,
+        config,
+        &admin,
+        Vec::new(),
+    )
+    .await?)
+}
+
+#[derive(FromForm)]
+pub struct ProfileForm<'r> {
+    display_name: FormResult<'r, DisplayName<'r>>,
+    email: FormResult<'r, Email<'r>>,
+    current_password: &'r str,
+}
+
+async fn check_current_password(
+    db: &mut Connection<Database>,
+    admin: &Admin,
+    password: &str,
+    messages: &mut Vec<Message>,
+) -> anyhow::Result<()> {
+    let hash = sqlx::query_scalar!("select password from admins where id = $1", &admin.id)
+        .fetch_one(&mut **db)
+        .await?;
+    if !verify_password(password, &hash)? {
+        messages.push(Message {
+            text_key: String::from("current-password-wrong"),
+            message_type: MessageType::Error,
+        });
+    }
+    Ok(())
+}
+
+#[post("/admin/profile", data = "<form>")]
+pub async fn profile_post(
+    lang: Language,
+    csrf: CsrfToken,
+    mut db: Connection<Database>,
+    config: &State<Config>,
+    admin: Admin,
+    form: CsrfForm<ProfileForm<'_>>,
+) -> RocketResult<Template> {
+    let ProfileForm {
+        display_name,
+        email,
+        current_password,
+    } = form.into_inner();
+    let mut messages = Vec::new();
+    let display_name = handle_form_error(display_name, &mut messages);
+    let email = handle_form_error(email, &mut messages);
+    if messages.is_empty() {
+        check_current_password(&mut db, &admin, current_password, &mut messages).await?;
+    }
+    if messages.is_empty()
+        && sqlx::query!(
+            "select id from admins where email = $1 and id <> $2",
+            email,
+            &admin.id
+        )
+        .fetch_optional(&mut *db)
+        .await?
+        .is_some()
+    {
+        messages.push(Message {
+            text_key: String::from("email-already-registered"),
+            message_type: MessageType::Error,
+        });
+    }
+    if messages.is_empty() {
+        sqlx::query!(
+            "update admins set display_name = $2, email = $3 where id = $1",
+            &admin.id,
+            display_name,
+            email,
+        )
+        .execute(&mut *db)
+        .await?;
+        audit::log(
+            &mut db,
+            Some(&admin.id),
+            "profile-update",
+            std::slice::from_ref(&admin.id),
+            json!({ "display_name": display_name, "email": email }),
+        )
+        .await?;
+        messages.push(Message {
+            text_key: String::from("profile-updated"),
+            message_type: MessageType::Success,
+        });
+    }
+    Ok(render_profile(lang.into_string(), csrf, &mut db, config, &admin, messages).await?)
+}
+
+#[derive(FromForm)]
+pub struct PasswordChangeForm<'r> {
+    current_password: &'r str,
+    new_password: FormResult<'r, Password<'r>>,
+}
+
+#[post("/admin/profile/password", data = "<form>")]
+pub async fn password_change_post(
+    lang: Language,
+    csrf: CsrfToken,
+    mut db: Connection<Database>,
+    config: &State<Config>,
+    admin: Admin,
+    form: CsrfForm<PasswordChangeForm<'_>>,
+) -> RocketResult<Template> {
+    let PasswordChangeForm {
+        current_password,
+        new_password,
+    } = form.into_inner();
+    let mut messages = Vec::new();
+    let new_password = handle_form_error(new_password, &mut messages);
+    if messages.is_empty() {
+        check_current_password(&mut db, &admin, current_password, &mut messages).await?;
+    }
+    if messages.is_empty() {
+        sqlx::query!(
+            "update admins set password = $2 where id = $1",
+            &admin.id,
+            hash_password(new_password)?,
+        )
+        .execute(&mut *db)
+        .await?;
+        // All other devices have to log in with the new password.
+        sqlx::query!(
+            "delete from sessions where admin_id = $1 and id <> $2",
+            &admin.id,
+            &admin.session
+        )
+        .execute(&mut *db)
+        .await?;
+        audit::log(
+            &mut db,
+            Some(&admin.id),
+            "password-change",
+            std::slice::from_ref(&admin.id),
+            json!({}),
+        )
+        .await?;
+        messages.push(Message {
+            text_key: String::from("password-changed"),
+            message_type: MessageType::Success,
+        });
+    }
+    Ok(render_profile(lang.into_string(), csrf, &mut db, config, &admin, messages).await?)
+}
+
+#[derive(FromForm)]
+pub struct CalendarForm<'r> {
+    button: &'r str,
+}
+
+#[post("/admin/profile/calendar", data = "<form>")]
+pub async fn calendar_post(
+    lang: Language,
+    csrf: CsrfToken,
+    mut db: Connection<Database>,
+    config: &State<Config>,
+    admin: Admin,
+    form: CsrfForm<CalendarForm<'_>>,
+) -> RocketResult<Result<Template, Status>> {
+    // A new token makes the links which have been shared before useless.
+    let (action, text_key) = match form.button {
+        "renew" => {
+            sqlx::query!(
+                "update admins set calendar_token = gen_random_uuid() where id = $1",
+                &admin.id
+            )
+            .execute(&mut *db)
+            .await?;
+            ("calendar-renew", "calendar-subscription-renewed")
+        }
+        "disable" => {
+            sqlx::query!(
+                "update admins set calendar_token = null where id = $1",
+                &admin.id
+            )
+            .execute(&mut *db)
+            .await?;
+            ("calendar-disable", "calendar-subscription-turned-off")
+        }
+        _ => return Ok(Err(Status::BadRequest)),
+    };
+    audit::log(
+        &mut db,
+        Some(&admin.id),
+        action,
+        std::slice::from_ref(&admin.id),
+        json!({}),
+    )
+    .await?;
+    let messages = vec![Message {
+        text_key: String::from(text_key),
+        message_type: MessageType::Success,
+    }];
+    Ok(Ok(render_profile(
+        lang.into_string(),
+        csrf,
+        &mut db,
+        config,
+        &admin,
+        messages,
+    )
+    .await?))
 }
 
 #[derive(FromForm)]
@@ -382,20 +1267,21 @@ pub struct RequestPasswordResetForm<'r> {
 }
 
 #[get("/admin/password-reset-request")]
-pub async fn password_reset_request_get(lang: Language) -> Template {
+pub async fn password_reset_request_get(lang: Language, csrf: CsrfToken) -> Template {
     Template::render(
         "password-reset-request",
-        context! { lang: lang.into_string(), email: "" },
+        context! { csrf_token: csrf.as_str(), lang: lang.into_string(), email: "" },
     )
 }
 
 #[post("/admin/password-reset-request", data = "<form>")]
 pub async fn password_reset_request_post<'r>(
     lang: Language,
+    csrf: CsrfToken,
     mut db: Connection<Database>,
-    mailer: &State<Mailer>,
     config: &State<Config>,
-    form: Form<RequestPasswordResetForm<'r>>,
+    ip: Option<IpAddr>,
+    form: CsrfForm<RequestPasswordResetForm<'r>>,
 ) -> RocketResult<Template> {
     let lang = lang.into_string();
     let RequestPasswordResetForm { email } = form.into_inner();
@@ -404,7 +1290,20 @@ pub async fn password_reset_request_post<'r>(
     if !messages.is_empty() {
         return Ok(Template::render(
             "password-reset-request",
-            context! { lang, email, messages },
+            context! { csrf_token: csrf.as_str(), lang, email, messages },
+        ));
+    }
+
+    let keys = rate_limit::keys(ip, email);
+    if !rate_limit::attempt(&mut db, RateLimitAction::PasswordResetRequest, &keys).await? {
+        return Ok(Template::render(
+            "password-reset-request",
+            context! {
+                csrf_token: csrf.as_str(),
+                lang,
+                email,
+                messages: [Message { text_key: String::from("rate-limited"), message_type: MessageType::Error }],
+            },
         ));
     }
 
@@ -414,17 +1313,30 @@ pub async fn password_reset_request_post<'r>(
         .map(|record| record.id);
 
     if let Some(admin_id) = admin_id {
+        let mut tx = db.begin().await?;
+        // Only the most recently requested link is valid.
+        sqlx::query!("delete from password_resets where admin_id = $1", &admin_id)
+            .execute(&mut *tx)
+            .await?;
+        audit::log(
+            &mut tx,
+            None,
+            "password-reset-request",
+            std::slice::from_ref(&admin_id),
+            json!({}),
+        )
+        .await?;
         let token = sqlx::query!(
             "insert into password_resets (admin_id) values ($1) returning token",
             &admin_id,
         )
-        .fetch_one(&mut *db)
+        .fetch_one(&mut *tx)
         .await?
         .token;
 
         send_mail(
+            &mut tx,
             config,
-            mailer,
             email,
             &lang,
             "mail-password-reset-subject",
@@ -441,11 +1353,13 @@ pub async fn password_reset_request_post<'r>(
             ),
         )
         .await?;
+        tx.commit().await?;
     }
 
     Ok(Template::render(
         "password-reset-request",
         context! {
+            csrf_token: csrf.as_str(),
             lang,
             email: "",
             messages: [Message { text_key: String::from("password-reset-sent"), message_type: MessageType::Success }]
@@ -460,20 +1374,46 @@ pub struct PasswordResetForm<'r> {
 }
 
 #[get("/admin/password-reset?<token>")]
-pub async fn password_reset_get(lang: Language, token: &str) -> Template {
-    Template::render(
-        "password-reset",
-        context! { lang: lang.into_string(), token },
+pub async fn password_reset_get(
+    lang: Language,
+    csrf: CsrfToken,
+    mut db: Connection<Database>,
+    config: &State<Config>,
+    token: &str,
+) -> RocketResult<Template> {
+    let expired = sqlx::query_scalar!(
+        r#"select created < now() - make_interval(hours => $2) as "expired!"
+        from password_resets where token = $1"#,
+        &token,
+        config.password_reset_lifetime_hours,
     )
+    .fetch_optional(&mut *db)
+    .await?;
+    Ok(match expired {
+        Some(true) => link_expired(lang.into_string(), "password-reset-expired"),
+        Some(false) => Template::render(
+            "password-reset",
+            context! { csrf_token: csrf.as_str(), lang: lang.into_string(), token },
+        ),
+        None => Template::render(
+            "password-reset",
+            context! {
+                csrf_token: csrf.as_str(),
+                lang: lang.into_string(),
+                token,
+                messages: [Message { text_key: String::from("password-reset-invalid"), message_type: MessageType::Error }],
+            },
+        ),
+    })
 }
 
 #[post("/admin/password-reset", data = "<form>")]
 pub async fn password_reset_post<'r>(
     lang: Language,
+    csrf: CsrfToken,
     mut db: Connection<Database>,
-    mailer: &State<Mailer>,
     config: &State<Config>,
-    form: Form<PasswordResetForm<'r>>,
+    form: CsrfForm<PasswordResetForm<'r>>,
 ) -> RocketResult<Result<Redirect, Template>> {
     let lang = lang.into_string();
     let PasswordResetForm { token, password } = form.into_inner();
@@ -482,32 +1422,48 @@ pub async fn password_reset_post<'r>(
     if !messages.is_empty() {
         return Ok(Err(Template::render(
             "password-reset",
-            context! { lang, token, messages },
+            context! { csrf_token: csrf.as_str(), lang, token, messages },
         )));
     }
 
     let id = sqlx::query!(
-        "delete from password_resets where token = $1 returning admin_id",
+        r#"delete from password_resets where token = $1
+        returning admin_id, created < now() - make_interval(hours => $2) as "expired!""#,
         &token,
+        config.password_reset_lifetime_hours,
     )
     .fetch_optional(&mut *db)
     .await?;
 
     match id {
+        Some(record) if record.expired => Ok(Err(link_expired(lang, "password-reset-expired"))),
         Some(record) => {
             let id = record.admin_id;
+            let mut tx = db.begin().await?;
             let email = sqlx::query!(
                 "update admins set password = $1 where id = $2 returning email",
                 &hash_password(password)?,
                 &id,
             )
-            .fetch_one(&mut *db)
+            .fetch_one(&mut *tx)
             .await?
             .email;
+            // Whoever knew the old password must not stay logged in.
+            sqlx::query!("delete from sessions where admin_id = $1", &id)
+                .execute(&mut *tx)
+                .await?;
+            audit::log(
+                &mut tx,
+                Some(&id),
+                "password-reset",
+                std::slice::from_ref(&id),
+                json!({}),
+            )
+            .await?;
 
             send_mail(
+                &mut tx,
                 config,
-                mailer,
                 &email,
                 &lang,
                 "mail-password-was-reset-subject",
@@ -520,6 +1476,7 @@ pub async fn password_reset_post<'r>(
                 ),
             )
             .await?;
+            tx.commit().await?;
 
             Ok(Ok(Redirect::to(uri!(login_get(
                 redirect = Option::<&str>::None
@@ -527,21 +1484,294 @@ pub async fn password_reset_post<'r>(
         }
         None => Ok(Err(Template::render(
             "password-reset",
-            context! { lang, token, messages: [Message { text_key: String::from("password-reset-invalid"), message_type: MessageType::Error }]},
+            context! {
+                csrf_token: csrf.as_str(),
+                lang,
+                token,
+                messages: [Message { text_key: String::from("password-reset-invalid"), message_type: MessageType::Error }],
+            },
         ))),
     }
 }
 
+fn link_expired(lang: String, text_key: &str) -> Template {
+    Template::render(
+        "link-expired",
+        context! {
+            lang,
+            password_reset: text_key == "password-reset-expired",
+            messages: [Message { text_key: String::from(text_key), message_type: MessageType::Error }],
+        },
+    )
+}
+
+/// Deletes all invites and password reset tokens which have expired.
+pub async fn purge_expired_tokens(
+    db: &sqlx::PgPool,
+    invite_lifetime_hours: i32,
+    password_reset_lifetime_hours: i32,
+) -> anyhow::Result<()> {
+    sqlx::query!(
+        "delete from invites where created < now() - make_interval(hours => $1)",
+        invite_lifetime_hours
+    )
+    .execute(db)
+    .await?;
+    sqlx::query!(
+        "delete from password_resets where created < now() - make_interval(hours => $1)",
+        password_reset_lifetime_hours
+    )
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize, FromFormField, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "admin_role", rename_all = "lowercase")]
+pub enum Role {
+    Owner,
+    Organizer,
+    Jury,
+    Viewer,
+}
+
+impl Role {
+    pub const ALL: [Role; 4] = [Role::Owner, Role::Organizer, Role::Jury, Role::Viewer];
+
+    pub fn permits(self, permission: Permission) -> bool {
+        match self {
+            Role::Owner => true,
+            Role::Organizer => !matches!(
+                permission,
+                Permission::ManageAdmins
+                    | Permission::ViewAuditLog
+                    | Permission::ManageDateTypes
+                    | Permission::ManageMails
+            ),
+            Role::Jury => permission == Permission::RecordResults,
+            Role::Viewer => false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    ManageDates,
+    ManageRooms,
+    ManageAnnouncements,
+    RecordResults,
+    SendResultMails,
+    ManageAdmins,
+    ViewAuditLog,
+    ManageDateTypes,
+    ManageMails,
+}
+
+impl Permission {
+    pub const ALL: [Permission; 9] = [
+        Permission::ManageDates,
+        Permission::ManageRooms,
+        Permission::ManageAnnouncements,
+        Permission::RecordResults,
+        Permission::SendResultMails,
+        Permission::ManageAdmins,
+        Permission::ViewAuditLog,
+        Permission::ManageDateTypes,
+        Permission::ManageMails,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Permission::ManageDates => "manage-dates",
+            Permission::ManageRooms => "manage-rooms",
+            Permission::ManageAnnouncements => "manage-announcements",
+            Permission::RecordResults => "record-results",
+            Permission::SendResultMails => "send-result-mails",
+            Permission::ManageAdmins => "manage-admins",
+            Permission::ViewAuditLog => "view-audit-log",
+            Permission::ManageDateTypes => "manage-date-types",
+            Permission::ManageMails => "manage-mails",
+        }
+    }
+}
+
+/// Type level permissions which can be required by the [`Authorized`] request guard.
+pub mod require {
+    use super::Permission;
+
+    pub trait RequiredPermission: Send + Sync + 'static {
+        const PERMISSION: Permission;
+    }
+
+    pub struct ManageDates;
+    pub struct ManageRooms;
+    pub struct ManageAnnouncements;
+    pub struct RecordResults;
+    pub struct SendResultMails;
+    pub struct ManageAdmins;
+    pub struct ViewAuditLog;
+    pub struct ManageDateTypes;
+    pub struct ManageMails;
+
+    impl RequiredPermission for ManageDates {
+        const PERMISSION: Permission = Permission::ManageDates;
+    }
+
+    impl RequiredPermission for ManageRooms {
+        const PERMISSION: Permission = Permission::ManageRooms;
+    }
+
+    impl RequiredPermission for ManageAnnouncements {
+        const PERMISSION: Permission = Permission::ManageAnnouncements;
+    }
+
+    impl RequiredPermission for RecordResults {
+        const PERMISSION: Permission = Permission::RecordResults;
+    }
+
+    impl RequiredPermission for SendResultMails {
+        const PERMISSION: Permission = Permission::SendResultMails;
+    }
+
+    impl RequiredPermission for ManageAdmins {
+        const PERMISSION: Permission = Permission::ManageAdmins;
+    }
+
+    impl RequiredPermission for ViewAuditLog {
+        const PERMISSION: Permission = Permission::ViewAuditLog;
+    }
+
+    impl RequiredPermission for ManageDateTypes {
+        const PERMISSION: Permission = Permission::ManageDateTypes;
+    }
+
+    impl RequiredPermission for ManageMails {
+        const PERMISSION: Permission = Permission::ManageMails;
+    }
+}
+
+#[derive(Clone)]
 pub struct Admin {
     pub id: String,
+    /// The session this admin is logged in with.
+    pub session: String,
+    pub role: Role,
+    /// The date types this admin is restricted to or `None` if there is no restriction.
+    pub date_types: Option<Vec<String>>,
+}
+
+impl Admin {
+    /// Loads the admin of a session and whether they still have to set up two-factor
+    /// authentication.
+    async fn load(db: &Database, session: &str) -> anyhow::Result<Option<(Admin, bool)>> {
+        let (id, role, two_factor_missing) = match sqlx::query!(
+            r#"select
+                admins.id,
+                admins.role as "role: Role",
+                not admins.totp_enabled and settings.require_two_factor as "two_factor_missing!"
+            from sessions
+            join admins on admins.id = sessions.admin_id
+            cross join settings
+            where sessions.id = $1 and sessions.valid_until > now()"#,
+            &session
+        )
+        .fetch_optional(&**db)
+        .await?
+        {
+            Some(record) => (record.id, record.role, record.two_factor_missing),
+            None => return Ok(None),
+        };
+        sqlx::query!(
+            "update sessions set last_seen = now() where id = $1 and last_seen < now() - interval '1 minute'",
+            &session
+        )
+        .execute(&**db)
+        .await?;
+        let date_types = sqlx::query_scalar!(
+            "select date_type from admins_date_types where admin_id = $1 order by date_type",
+            &id
+        )
+        .fetch_all(&**db)
+        .await?;
+        let admin = Admin {
+            id,
+            session: String::from(session),
+            role,
+            date_types: if date_types.is_empty() {
+                None
+            } else {
+                Some(date_types)
+            },
+        };
+        Ok(Some((admin, two_factor_missing)))
+    }
+
+    pub fn can(&self, permission: Permission) -> bool {
+        // Date types and mails are shared by everyone, so admins restricted to some date types
+        // can't manage them. The audit log contains the actions on all date types.
+        if matches!(
+            permission,
+            Permission::ViewAuditLog | Permission::ManageDateTypes | Permission::ManageMails
+        ) && self.date_types.is_some()
+        {
+            return false;
+        }
+        self.role.permits(permission)
+    }
+
+    pub fn may_access(&self, date_type: &str) -> bool {
+        match &self.date_types {
+            Some(date_types) => date_types.iter().any(|allowed| allowed == date_type),
+            None => true,
+        }
+    }
+
+    /// Whether the admin may manage another admin or an invite with the given date types. Restricted
+    /// admins may only manage the ones which are restricted to their date types, too.
+    pub fn may_manage(&self, date_types: &[String]) -> bool {
+        self.date_types.is_none()
+            || (!date_types.is_empty()
+                && date_types
+                    .iter()
+                    .all(|date_type| self.may_access(date_type)))
+    }
+
+    pub async fn accessible_date_types(
+        &self,
+        db: &mut Connection<Database>,
+        lang: &str,
+    ) -> anyhow::Result<Vec<DateType>> {
+        Ok(DateType::get_variants(db, lang)
+            .await?
+            .into_iter()
+            .filter(|date_type| self.may_access(&date_type.value))
+            .collect())
+    }
+
+    /// Names of all granted permissions, used by the templates to hide unavailable actions.
+    pub fn permissions(&self) -> Vec<&'static str> {
+        Permission::ALL
+            .into_iter()
+            .filter(|permission| self.can(*permission))
+            .map(Permission::name)
+            .collect()
+    }
+}
+
+/// Request guard for any logged-in admin, including admins who are not allowed to do anything
+/// before setting up two-factor authentication. Most routes should use [`Admin`] instead.
+pub struct LoggedIn {
+    pub admin: Admin,
+    pub two_factor_missing: bool,
 }
 
 #[rocket::async_trait]
-impl<'r> FromRequest<'r> for Admin {
+impl<'r> FromRequest<'r> for LoggedIn {
     type Error = anyhow::Error;
 
     async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
-        use rocket::outcome::Outcome::{Failure, Success};
+        use rocket::outcome::Outcome::{Failure, Forward, Success};
 
         let cookies = req.guard::<&CookieJar<'r>>().await.unwrap();
 
@@ -553,10 +1783,73 @@ impl<'r> FromRequest<'r> for Admin {
             None => return Failure((Status::Unauthorized, anyhow!("No login cookie present!"))),
         };
 
-        if login.valid_until > Utc::now() {
-            Success(Admin { id: login.id })
-        } else {
-            Failure((Status::Unauthorized, anyhow!("Invalid login cookie!")))
+        let db = match req.guard::<&Database>().await {
+            Success(db) => db,
+            Failure((status, _)) => return Failure((status, anyhow!("No database available!"))),
+            Forward(()) => return Forward(()),
+        };
+        match Admin::load(db, &login.session).await {
+            Ok(Some((admin, two_factor_missing))) => Success(LoggedIn {
+                admin,
+                two_factor_missing,
+            }),
+            Ok(None) => Failure((Status::Unauthorized, anyhow!("Unknown or expired session!"))),
+            Err(error) => Failure((Status::InternalServerError, error)),
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Admin {
+    type Error = anyhow::Error;
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        use rocket::outcome::Outcome::{Failure, Forward, Success};
+
+        match req.guard::<LoggedIn>().await {
+            Success(LoggedIn {
+                two_factor_missing: true,
+                ..
+            }) => Failure((
+                Status::PreconditionRequired,
+                anyhow!("Two-factor authentication has to be set up first!"),
+            )),
+            Success(LoggedIn { admin, .. }) => Success(admin),
+            Failure(failure) => Failure(failure),
+            Forward(()) => Forward(()),
+        }
+    }
+}
+
+/// Request guard for an admin who has the permission `P`.
+pub struct Authorized<P> {
+    pub admin: Admin,
+    _permission: PhantomData<P>,
+}
+
+impl<P> Deref for Authorized<P> {
+    type Target = Admin;
+
+    fn deref(&self) -> &Self::Target {
+        &self.admin
+    }
+}
+
+#[rocket::async_trait]
+impl<'r, P: RequiredPermission> FromRequest<'r> for Authorized<P> {
+    type Error = anyhow::Error;
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        use rocket::outcome::Outcome::{Failure, Forward, Success};
+
+        match req.guard::<Admin>().await {
+            Success(admin) if admin.can(P::PERMISSION) => Success(Authorized {
+                admin,
+                _permission: PhantomData,
+            }),
+            Success(_) => Failure((Status::Forbidden, anyhow!("Missing permission!"))),
+            Failure(failure) => Failure(failure),
+            Forward(()) => Forward(()),
         }
     }
 }
@@ -566,3 +1859,14 @@ pub async fn unauthorized_handler(req: &Request<'_>) -> Redirect {
     let to = req.uri().to_string();
     Redirect::to(uri!(login_get(redirect = Some(to))))
 }
+
+#[catch(428)]
+pub async fn two_factor_missing_handler() -> Redirect {
+    Redirect::to(uri!(crate::two_factor::two_factor_get))
+}
+
+#[catch(403)]
+pub async fn forbidden_handler(req: &Request<'_>) -> Template {
+    let lang = Language::from_request(req).await.unwrap().into_string();
+    Template::render("forbidden", context! { lang })
+}
@@ -0,0 +1,159 @@
+use std::ops::Deref;
+
+use rand_core::{OsRng, RngCore};
+use rocket::data::{self, Data, FromData};
+use rocket::form::{self, DataField, Errors, Form, FromForm, Options, ValueField};
+use rocket::http::{Cookie, CookieJar, Status};
+use rocket::request::{self, FromRequest};
+use rocket::Request;
+
+const COOKIE_NAME: &str = "csrf";
+const FIELD_NAME: &str = "csrf_token";
+
+/// Per-session token which has to be submitted with every form.
+///
+/// The token is stored in a private cookie and created on first use. Templates include it via
+/// `{% include "csrf" %}`, which expects it as `csrf_token` in the context.
+pub struct CsrfToken(String);
+
+impl CsrfToken {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for CsrfToken {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let cookies = req.guard::<&CookieJar<'r>>().await.unwrap();
+        let token = match cookies.get_private(COOKIE_NAME) {
+            Some(cookie) => String::from(cookie.value()),
+            None => {
+                let token = generate_token();
+                cookies.add_private(
+                    Cookie::build(COOKIE_NAME, token.clone())
+                        .http_only(true)
+                        .expires(None)
+                        .finish(),
+                );
+                token
+            }
+        };
+        request::Outcome::Success(CsrfToken(token))
+    }
+}
+
+/// Form data guard which only succeeds if the submitted `csrf_token` matches the session's token.
+///
+/// All other fields are parsed into `T` exactly like [`Form`] would do.
+pub struct CsrfForm<T>(T);
+
+impl<T> CsrfForm<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for CsrfForm<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+struct CsrfFields<'r, T> {
+    token: Option<&'r str>,
+    value: T,
+}
+
+#[rocket::async_trait]
+impl<'r, T: FromForm<'r>> FromForm<'r> for CsrfFields<'r, T> {
+    type Context = (Option<&'r str>, T::Context);
+
+    fn init(opts: Options) -> Self::Context {
+        (None, T::init(opts))
+    }
+
+    fn push_value(ctxt: &mut Self::Context, field: ValueField<'r>) {
+        if field.name.source() == FIELD_NAME {
+            ctxt.0 = Some(field.value);
+        } else {
+            T::push_value(&mut ctxt.1, field);
+        }
+    }
+
+    async fn push_data(ctxt: &mut Self::Context, field: DataField<'r, '_>) {
+        T::push_data(&mut ctxt.1, field).await;
+    }
+
+    fn push_error(ctxt: &mut Self::Context, error: form::Error<'r>) {
+        T::push_error(&mut ctxt.1, error);
+    }
+
+    fn finalize(ctxt: Self::Context) -> form::Result<'r, Self> {
+        Ok(CsrfFields {
+            token: ctxt.0,
+            value: T::finalize(ctxt.1)?,
+        })
+    }
+}
+
+#[rocket::async_trait]
+impl<'r, T: FromForm<'r>> FromData<'r> for CsrfForm<T> {
+    type Error = Errors<'r>;
+
+    async fn from_data(req: &'r Request<'_>, data: Data<'r>) -> data::Outcome<'r, Self> {
+        use rocket::outcome::Outcome::{Failure, Forward, Success};
+
+        let fields = match Form::<CsrfFields<'r, T>>::from_data(req, data).await {
+            Success(form) => form.into_inner(),
+            Failure(failure) => return Failure(failure),
+            Forward(data) => return Forward(data),
+        };
+        let expected = req.cookies().get_private(COOKIE_NAME);
+        match (expected, fields.token) {
+            (Some(expected), Some(token)) if constant_time_eq(expected.value(), token) => {
+                Success(CsrfForm(fields.value))
+            }
+            _ => Failure((
+                Status::Forbidden,
+                form::Error::validation("csrf-invalid").into(),
+            )),
+        }
+    }
+}
+
+/// Form without any fields for POST requests which only carry the CSRF token.
+pub struct NoFields;
+
+#[rocket::async_trait]
+impl<'r> FromForm<'r> for NoFields {
+    type Context = ();
+
+    fn init(_opts: Options) -> Self::Context {}
+
+    fn push_value(_ctxt: &mut Self::Context, _field: ValueField<'r>) {}
+
+    async fn push_data(_ctxt: &mut Self::Context, _field: DataField<'r, '_>) {}
+
+    fn finalize(_ctxt: Self::Context) -> form::Result<'r, Self> {
+        Ok(NoFields)
+    }
+}
@@ -0,0 +1,164 @@
+//! Comma separated values as described in RFC 4180.
+
+/// Appends a record to `out`, quoting fields where necessary.
+///
+/// Fields which spreadsheet applications would interpret as a formula are prefixed with an
+/// apostrophe, so exported notes can't run anything on the computer of an admin.
+pub fn write_record<S: AsRef<str>>(out: &mut String, separator: char, fields: &[S]) {
+    for (index, field) in fields.iter().enumerate() {
+        if index > 0 {
+            out.push(separator);
+        }
+        let mut field = field.as_ref();
+        let prefixed;
+        if field.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+            prefixed = format!("'{}", field);
+            field = &prefixed;
+        }
+        if field.contains([separator, '"', '\r', '\n']) {
+            out.push('"');
+            out.push_str(&field.replace('"', "\"\""));
+            out.push('"');
+        } else {
+            out.push_str(field);
+        }
+    }
+    out.push_str("\r\n");
+}
+
+/// Splits `input` into records of fields, removing the quotes around fields.
+///
+/// Every line is a record, so their positions match the line numbers of spreadsheet
+/// applications, empty lines result in a single empty field. A missing closing quote ends the
+/// field at the end of the input, as spreadsheet applications are rather lenient about the files
+/// they write.
+pub fn read_records(input: &str, separator: char) -> Vec<Vec<String>> {
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut characters = input.chars().peekable();
+    while let Some(character) = characters.next() {
+        match character {
+            '"' if quoted => {
+                if characters.peek() == Some(&'"') {
+                    characters.next();
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.is_empty() => quoted = true,
+            '\r' | '\n' if !quoted => {
+                if character == '\r' && characters.peek() == Some(&'\n') {
+                    characters.next();
+                }
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            character if character == separator && !quoted => {
+                record.push(std::mem::take(&mut field));
+            }
+            character => field.push(character),
+        }
+    }
+    // The line break at the end of the last record doesn't start another one.
+    if !record.is_empty() || !field.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(separator: char, fields: &[&str]) -> String {
+        let mut out = String::new();
+        write_record(&mut out, separator, fields);
+        out
+    }
+
+    #[test]
+    fn writes_plain_fields_unquoted() {
+        assert_eq!(write(',', &["a", "b c", ""]), "a,b c,\r\n");
+        assert_eq!(write(';', &["a,b", "c"]), "a,b;c\r\n");
+    }
+
+    #[test]
+    fn quotes_fields_with_special_characters() {
+        assert_eq!(write(',', &["a,b", "c"]), "\"a,b\",c\r\n");
+        assert_eq!(write(';', &["a;b"]), "\"a;b\"\r\n");
+        assert_eq!(write(',', &["say \"hi\""]), "\"say \"\"hi\"\"\"\r\n");
+        assert_eq!(
+            write(',', &["one\ntwo", "x\ry"]),
+            "\"one\ntwo\",\"x\ry\"\r\n"
+        );
+    }
+
+    #[test]
+    fn prefixes_formulas() {
+        assert_eq!(write(',', &["=1+1"]), "'=1+1\r\n");
+        assert_eq!(
+            write(',', &["+49 123", "-1", "@sum"]),
+            "'+49 123,'-1,'@sum\r\n"
+        );
+        assert_eq!(write(',', &["=a,b"]), "\"'=a,b\"\r\n");
+        assert_eq!(write(',', &["a=b"]), "a=b\r\n");
+    }
+
+    #[test]
+    fn reads_plain_records() {
+        assert_eq!(
+            read_records("a,b\r\nc,d\ne,f", ','),
+            [vec!["a", "b"], vec!["c", "d"], vec!["e", "f"]]
+        );
+        assert_eq!(read_records("a;b,c\n", ';'), [vec!["a", "b,c"]]);
+        assert_eq!(
+            read_records("a,,\n\nb", ','),
+            [vec!["a", "", ""], vec![""], vec!["b"]]
+        );
+        assert!(read_records("", ',').is_empty());
+    }
+
+    #[test]
+    fn reads_quoted_fields() {
+        assert_eq!(
+            read_records("\"a,b\",\"c\"\"d\"\"\",\"\"\n", ','),
+            [vec!["a,b", "c\"d\"", ""]]
+        );
+        // Quotes within a field which doesn't start with one are kept.
+        assert_eq!(read_records("a\"b,c", ','), [vec!["a\"b", "c"]]);
+    }
+
+    #[test]
+    fn reads_embedded_line_breaks() {
+        assert_eq!(
+            read_records("\"one\r\ntwo\",x\r\ny,z\r\n", ','),
+            [vec!["one\r\ntwo", "x"], vec!["y", "z"]]
+        );
+    }
+
+    #[test]
+    fn reads_unterminated_quotes_to_the_end() {
+        assert_eq!(read_records("a,\"b\nc", ','), [vec!["a", "b\nc"]]);
+    }
+
+    #[test]
+    fn skips_the_byte_order_mark() {
+        assert_eq!(read_records("\u{feff}a,b\n", ','), [vec!["a", "b"]]);
+        assert_eq!(read_records("\u{feff}\"a\",b\n", ','), [vec!["a", "b"]]);
+    }
+
+    #[test]
+    fn reads_written_records() {
+        let fields = ["Doe, \"Jo\"", "line\r\nbreak", "=cmd", "plain", ""];
+        let mut out = String::new();
+        write_record(&mut out, ';', &fields);
+        write_record(&mut out, ';', &fields);
+        let expected = ["Doe, \"Jo\"", "line\r\nbreak", "'=cmd", "plain", ""];
+        assert_eq!(read_records(&out, ';'), [expected, expected]);
+    }
+}
@@ -0,0 +1,236 @@
+//! iCalendar files for booked dates and calendar subscriptions, see RFC 5545 and RFC 5546.
+
+use chrono::{DateTime, Local, Offset, Utc};
+
+/// A date as it appears in the calendar of a candidate or an admin.
+pub struct Event<'a> {
+    /// Stays the same for all updates of the booking, so calendars can match them.
+    pub uid: String,
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    pub summary: &'a str,
+    pub location: &'a str,
+    pub description: &'a str,
+    pub organizer: &'a str,
+    pub attendee: &'a str,
+    /// Whether the date is not certain yet, like a date which hasn't been booked.
+    pub tentative: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    Publish,
+    Cancel,
+}
+
+impl Method {
+    fn name(self) -> &'static str {
+        match self {
+            Method::Publish => "PUBLISH",
+            Method::Cancel => "CANCEL",
+        }
+    }
+
+    /// The MIME type of calendar attachments with this method.
+    pub fn content_type(self) -> String {
+        format!("text/calendar; charset=utf-8; method={}", self.name())
+    }
+}
+
+/// Builds a globally unique id from an id which is unique in this application and the host of
+/// the web address.
+pub fn uid(id: &str, web_address: &str) -> String {
+    let host = web_address
+        .split_once("://")
+        .map_or(web_address, |(_scheme, rest)| rest)
+        .split(['/', ':'])
+        .next()
+        .unwrap_or_default();
+    format!("{}@{}", id, host)
+}
+
+/// Renders a calendar with a single event.
+///
+/// Times are given in the server's time zone. The time zone definition only contains the offset
+/// at the start of the event, calendar applications use their own rules for known time zone ids.
+pub fn render(event: &Event, method: Method) -> String {
+    let timezone = iana_time_zone::get_timezone().ok();
+    let mut lines = header(method);
+    if let Some(timezone) = &timezone {
+        let offset = event.start.offset().fix().local_minus_utc();
+        let offset = format!(
+            "{}{:02}{:02}",
+            if offset < 0 { '-' } else { '+' },
+            offset.abs() / 3600,
+            offset.abs() % 3600 / 60
+        );
+        lines.extend([
+            String::from("BEGIN:VTIMEZONE"),
+            format!("TZID:{}", timezone),
+            String::from("BEGIN:STANDARD"),
+            String::from("DTSTART:19700101T000000"),
+            format!("TZOFFSETFROM:{}", offset),
+            format!("TZOFFSETTO:{}", offset),
+            String::from("END:STANDARD"),
+            String::from("END:VTIMEZONE"),
+        ]);
+    }
+    lines.extend(render_event(event, method, timezone.as_deref()));
+    finish(lines)
+}
+
+/// Renders a calendar which applications subscribe to and refresh regularly.
+///
+/// Times are given in UTC, as the events span daylight saving time changes which the time zone
+/// definition of [`render`] can't describe.
+pub fn render_feed(name: &str, events: &[Event]) -> String {
+    let mut lines = header(Method::Publish);
+    lines.extend([
+        format!("X-WR-CALNAME:{}", escape(name)),
+        String::from("REFRESH-INTERVAL;VALUE=DURATION:PT1H"),
+        String::from("X-PUBLISHED-TTL:PT1H"),
+    ]);
+    for event in events {
+        lines.extend(render_event(event, Method::Publish, None));
+    }
+    finish(lines)
+}
+
+fn header(method: Method) -> Vec<String> {
+    vec![
+        String::from("BEGIN:VCALENDAR"),
+        String::from("VERSION:2.0"),
+        String::from("PRODID:-//audition-dates//EN"),
+        format!("METHOD:{}", method.name()),
+    ]
+}
+
+fn render_event(event: &Event, method: Method, timezone: Option<&str>) -> Vec<String> {
+    let format_time = |time: &DateTime<Local>| match timezone {
+        Some(timezone) => format!(";TZID={}:{}", timezone, time.format("%Y%m%dT%H%M%S")),
+        None => format!(":{}", time.with_timezone(&Utc).format("%Y%m%dT%H%M%SZ")),
+    };
+    let mut lines = vec![
+        String::from("BEGIN:VEVENT"),
+        format!("UID:{}", escape(&event.uid)),
+        format!("DTSTAMP:{}", Utc::now().format("%Y%m%dT%H%M%SZ")),
+        format!("DTSTART{}", format_time(&event.start)),
+        format!("DTEND{}", format_time(&event.end)),
+        format!("SUMMARY:{}", escape(event.summary)),
+        format!("LOCATION:{}", escape(event.location)),
+        format!("ORGANIZER:mailto:{}", event.organizer),
+    ];
+    if !event.description.is_empty() {
+        lines.push(format!("DESCRIPTION:{}", escape(event.description)));
+    }
+    match method {
+        Method::Publish => lines.extend([
+            String::from("SEQUENCE:0"),
+            String::from(if event.tentative {
+                "STATUS:TENTATIVE"
+            } else {
+                "STATUS:CONFIRMED"
+            }),
+        ]),
+        Method::Cancel => lines.extend([
+            format!("ATTENDEE:mailto:{}", event.attendee),
+            String::from("SEQUENCE:1"),
+            String::from("STATUS:CANCELLED"),
+        ]),
+    }
+    lines.push(String::from("END:VEVENT"));
+    lines
+}
+
+fn finish(mut lines: Vec<String>) -> String {
+    lines.push(String::from("END:VCALENDAR"));
+    lines.iter().map(|line| fold(line)).collect()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Splits a content line into lines of at most 75 bytes, terminated by CRLF.
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + line.len() / 70 * 3 + 2);
+    let mut length = 0;
+    for character in line.chars() {
+        if length + character.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(character);
+        length += character.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unfold(folded: &str) -> String {
+        folded.trim_end_matches("\r\n").replace("\r\n ", "")
+    }
+
+    #[test]
+    fn keeps_short_lines() {
+        assert_eq!(fold(""), "\r\n");
+        let line = "a".repeat(75);
+        assert_eq!(fold(&line), format!("{}\r\n", line));
+    }
+
+    #[test]
+    fn folds_after_75_octets() {
+        let line = "a".repeat(76);
+        assert_eq!(fold(&line), format!("{}\r\n a\r\n", "a".repeat(75)));
+        // Continuation lines start with a space, which counts towards their length.
+        let line = "a".repeat(75 + 74 + 1);
+        assert_eq!(
+            fold(&line),
+            format!("{}\r\n {}\r\n a\r\n", "a".repeat(75), "a".repeat(74))
+        );
+    }
+
+    #[test]
+    fn folds_between_characters() {
+        // The two octets of "ä" would cross the fold point, so it starts the next line.
+        let line = format!("{}ä", "a".repeat(74));
+        assert_eq!(fold(&line), format!("{}\r\n ä\r\n", "a".repeat(74)));
+        let line = format!("{}ä", "a".repeat(73));
+        assert_eq!(fold(&line), format!("{}\r\n", line));
+
+        let line = "DESCRIPTION:".to_owned() + &"Grüße 🎶 aus Köln, ".repeat(20);
+        let folded = fold(&line);
+        for part in folded.split_terminator("\r\n") {
+            assert!(part.len() <= 75, "{:?} is too long", part);
+        }
+        assert!(folded.split_terminator("\r\n").count() > 1);
+        assert_eq!(unfold(&folded), line);
+    }
+
+    #[test]
+    fn escapes_text() {
+        assert_eq!(escape("plain text"), "plain text");
+        assert_eq!(escape("a,b;c"), r"a\,b\;c");
+        assert_eq!(escape(r"back\slash"), r"back\\slash");
+        assert_eq!(escape(r"\,"), r"\\\,");
+        assert_eq!(escape("one\ntwo\r\nthree"), r"one\ntwo\nthree");
+        assert_eq!(escape("a:b"), "a:b");
+    }
+
+    #[test]
+    fn builds_uids_from_the_host() {
+        assert_eq!(
+            uid("booking-1", "https://dates.example.com:8000/path"),
+            "booking-1@dates.example.com"
+        );
+        assert_eq!(uid("booking-1", "localhost"), "booking-1@localhost");
+    }
+}
@@ -0,0 +1,200 @@
+use std::time::Duration;
+
+use anyhow::anyhow;
+use chrono::{DateTime, Utc};
+use rocket::tokio;
+use sqlx::{PgConnection, PgPool};
+
+use crate::{auth, mail, rate_limit, Config, Mailer};
+
+/// How often the database is checked for due jobs.
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+/// How long a job is locked for the worker running it. Longer running jobs may run twice.
+const LOCK_SECONDS: i32 = 10 * 60;
+/// Failed one-off jobs are given up after this many attempts.
+const MAX_ATTEMPTS: i32 = 10;
+
+/// The kinds of work the scheduler can do, stored by name in the `jobs` table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    PurgeExpiredTokens,
+    PublishDates,
+    SendReminders,
+    DeliverMails,
+}
+
+impl Kind {
+    const ALL: [Kind; 4] = [
+        Kind::PurgeExpiredTokens,
+        Kind::PublishDates,
+        Kind::SendReminders,
+        Kind::DeliverMails,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Kind::PurgeExpiredTokens => "purge-expired-tokens",
+            Kind::PublishDates => "publish-dates",
+            Kind::SendReminders => "send-reminders",
+            Kind::DeliverMails => "deliver-mails",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+
+    /// The interval in seconds of jobs which run periodically.
+    fn recurring_interval(self) -> Option<i32> {
+        match self {
+            Kind::PurgeExpiredTokens => Some(60 * 60),
+            Kind::PublishDates => None,
+            Kind::SendReminders => Some(5 * 60),
+            // Queued mails are picked up at the next poll, so queueing them doesn't have to touch
+            // the job, which would lock it until the transaction of the mail is committed.
+            Kind::DeliverMails => Some(POLL_INTERVAL.as_secs() as i32),
+        }
+    }
+}
+
+/// Schedules a one-off job to run at `run_at`.
+pub async fn enqueue(
+    db: &mut PgConnection,
+    kind: Kind,
+    run_at: DateTime<Utc>,
+) -> anyhow::Result<()> {
+    sqlx::query!(
+        "insert into jobs (kind, run_at) values ($1, $2)",
+        kind.name(),
+        run_at
+    )
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+pub struct Scheduler {
+    db: PgPool,
+    config: Config,
+    mailer: Mailer,
+}
+
+impl Scheduler {
+    pub fn new(db: PgPool, config: Config, mailer: Mailer) -> Self {
+        Scheduler { db, config, mailer }
+    }
+
+    /// Registers the recurring jobs and runs due jobs in the background from now on.
+    pub async fn spawn(self) -> anyhow::Result<()> {
+        for kind in Kind::ALL {
+            if let Some(interval) = kind.recurring_interval() {
+                sqlx::query!(
+                    "insert into jobs (kind, interval_seconds) values ($1, $2) \
+                    on conflict (kind) where interval_seconds is not null \
+                    do update set interval_seconds = excluded.interval_seconds",
+                    kind.name(),
+                    interval
+                )
+                .execute(&self.db)
+                .await?;
+            }
+        }
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(POLL_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(error) = self.run_due().await {
+                    error!("Could not run scheduled jobs: {:?}", error);
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Runs jobs until none is due anymore.
+    async fn run_due(&self) -> anyhow::Result<()> {
+        // Jobs locked by other workers are skipped instead of waited for.
+        while let Some(job) = sqlx::query!(
+            "update jobs set locked_until = now() + make_interval(secs => $1), attempts = attempts + 1 \
+            where id = ( \
+                select id from jobs \
+                where run_at <= now() and (locked_until is null or locked_until < now()) \
+                order by run_at \
+                limit 1 \
+                for update skip locked \
+            ) \
+            returning id, kind, interval_seconds, attempts",
+            LOCK_SECONDS as f64
+        )
+        .fetch_optional(&self.db)
+        .await?
+        {
+            let result = match Kind::from_name(&job.kind) {
+                Some(kind) => self.run(kind).await,
+                None => Err(anyhow!("Unknown job kind {}!", job.kind)),
+            };
+            match (result, job.interval_seconds) {
+                (Ok(()), Some(_)) => {
+                    sqlx::query!(
+                        "update jobs set run_at = now() + make_interval(secs => interval_seconds), \
+                        locked_until = null, attempts = 0, last_error = null \
+                        where id = $1",
+                        job.id
+                    )
+                    .execute(&self.db)
+                    .await?;
+                }
+                (Ok(()), None) => {
+                    sqlx::query!("delete from jobs where id = $1", job.id)
+                        .execute(&self.db)
+                        .await?;
+                }
+                (Err(error), None) if job.attempts >= MAX_ATTEMPTS => {
+                    error!("Giving up job {} ({}): {:?}", job.id, job.kind, error);
+                    sqlx::query!("delete from jobs where id = $1", job.id)
+                        .execute(&self.db)
+                        .await?;
+                }
+                (Err(error), _) => {
+                    error!("Job {} ({}) failed: {:?}", job.id, job.kind, error);
+                    // Retry with an exponential backoff, but not later than the next regular run.
+                    sqlx::query!(
+                        "update jobs set \
+                        run_at = now() + make_interval(secs => least(60 * power(2, attempts - 1), coalesce(interval_seconds, 3600))), \
+                        locked_until = null, last_error = $2 \
+                        where id = $1",
+                        job.id,
+                        format!("{:?}", error)
+                    )
+                    .execute(&self.db)
+                    .await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn run(&self, kind: Kind) -> anyhow::Result<()> {
+        match kind {
+            Kind::PurgeExpiredTokens => {
+                auth::purge_expired_tokens(
+                    &self.db,
+                    self.config.invite_lifetime_hours,
+                    self.config.password_reset_lifetime_hours,
+                )
+                .await?;
+                rate_limit::purge_expired(&self.db).await
+            }
+            Kind::PublishDates => {
+                let mut db = self.db.acquire().await?;
+                mail::notify_published_dates(&mut db, &self.config).await
+            }
+            Kind::SendReminders => {
+                let mut db = self.db.acquire().await?;
+                mail::send_reminders(&mut db, &self.config).await
+            }
+            Kind::DeliverMails => mail::deliver_mails(&self.db, &self.config, &self.mailer).await,
+        }
+    }
+}
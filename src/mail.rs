@@ -1,40 +1,274 @@
 use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
 
 use fluent_templates::fluent_bundle::FluentValue;
 use futures::TryStreamExt;
+use itertools::Itertools;
 use lettre::{
     message::{
         header::{self, ContentTransferEncoding},
-        IntoBody,
+        Attachment, IntoBody, Mailbox, MultiPart,
     },
-    AsyncTransport, Message,
+    transport::{smtp::authentication::Credentials, stub::AsyncStubTransport},
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
 };
 use map_macro::hash_map;
-use rocket_db_pools::Connection;
 use rocket_dyn_templates::context;
+use serde::Deserialize;
+use sqlx::{Connection, PgConnection, PgPool};
 use tera::Context;
 
 use crate::{
+    ics, jobs,
     language::LOCALES,
-    model::{check_date_type_access, Date, DateType},
-    Config, Database, Mailer, MAIL_TEMPLATES,
+    model::{check_date_type_access, get_announcement, Date, DateType},
+    Config, MAIL_TEMPLATES,
 };
 use anyhow::anyhow;
+use anyhow::Context as _;
 use anyhow::Result;
+use chrono::{DateTime, Local, Utc};
+use rand_core::{OsRng, RngCore};
+use rocket::tokio;
+
+/// How the connection to the mail server is encrypted.
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    /// Unencrypted, only suitable for a relay on the same host or network.
+    #[default]
+    None,
+    /// Upgrades a plain connection, usually on port 587.
+    Starttls,
+    /// TLS from the start, usually on port 465.
+    Wrapper,
+}
+
+/// Where outgoing mails are delivered to.
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    #[default]
+    Smtp,
+    /// Writes every mail as `.eml` file into `email_directory`.
+    File,
+    /// Keeps every mail in memory, for tests.
+    Stub,
+}
+
+#[derive(Clone)]
+pub enum Mailer {
+    Smtp(AsyncSmtpTransport<Tokio1Executor>),
+    File(PathBuf),
+    /// The sent mails can be inspected with [`AsyncStubTransport::messages`].
+    Stub(AsyncStubTransport),
+}
+
+impl Mailer {
+    /// Builds the transport selected by the `email_*` settings.
+    pub fn new(config: &Config) -> Result<Self> {
+        match config.email_transport {
+            Transport::Smtp => Ok(Mailer::Smtp(build_smtp_transport(config)?)),
+            Transport::File => {
+                let directory = config.email_directory.clone().ok_or_else(|| {
+                    anyhow!("email_directory has to be set for the file transport!")
+                })?;
+                fs::create_dir_all(&directory)
+                    .with_context(|| format!("Could not create {}!", directory.display()))?;
+                Ok(Mailer::File(directory))
+            }
+            Transport::Stub => Ok(Mailer::Stub(AsyncStubTransport::new_ok())),
+        }
+    }
+
+    /// A human readable description of where mails are delivered to, for logging.
+    pub fn describe(&self, config: &Config) -> String {
+        match self {
+            Mailer::Smtp(_) => format!("mail server {}:{}", config.email_host, config.email_port),
+            Mailer::File(directory) => format!("mail directory {}", directory.display()),
+            Mailer::Stub(_) => String::from("mail stub"),
+        }
+    }
+
+    pub async fn send(&self, message: Message) -> Result<()> {
+        match self {
+            Mailer::Smtp(transport) => {
+                transport.send(message).await?;
+            }
+            Mailer::File(directory) => {
+                // The time keeps the files in order, the random part makes the name unique.
+                let path = directory.join(format!(
+                    "{}-{:016x}.eml",
+                    Utc::now().format("%Y%m%dT%H%M%S%.6f"),
+                    OsRng.next_u64()
+                ));
+                tokio::fs::write(&path, message.formatted())
+                    .await
+                    .with_context(|| format!("Could not write {}!", path.display()))?;
+            }
+            Mailer::Stub(transport) => {
+                transport.send(message).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks whether mails can currently be delivered.
+    pub async fn test_connection(&self) -> Result<bool> {
+        match self {
+            Mailer::Smtp(transport) => Ok(transport.test_connection().await?),
+            Mailer::File(directory) => Ok(tokio::fs::metadata(directory).await?.is_dir()),
+            Mailer::Stub(_) => Ok(true),
+        }
+    }
+}
+
+fn build_smtp_transport(config: &Config) -> Result<AsyncSmtpTransport<Tokio1Executor>> {
+    type Smtp = AsyncSmtpTransport<Tokio1Executor>;
+    let builder = match config.email_tls {
+        SmtpTls::None => Smtp::builder_dangerous(&config.email_host),
+        SmtpTls::Starttls => Smtp::starttls_relay(&config.email_host)?,
+        SmtpTls::Wrapper => Smtp::relay(&config.email_host)?,
+    }
+    .port(config.email_port);
+    let password = match (&config.email_password, &config.email_password_file) {
+        (Some(_), Some(_)) => {
+            return Err(anyhow!(
+                "Only one of email_password and email_password_file may be set!"
+            ))
+        }
+        (Some(password), None) => Some(password.clone()),
+        (None, Some(path)) => Some(
+            fs::read_to_string(path)
+                .with_context(|| format!("Could not read {}!", path.display()))?
+                .trim_end_matches(['\r', '\n'])
+                .to_string(),
+        ),
+        (None, None) => None,
+    };
+    let builder = match (&config.email_username, password) {
+        (Some(username), Some(password)) => {
+            builder.credentials(Credentials::new(username.clone(), password))
+        }
+        (None, None) => builder,
+        _ => {
+            return Err(anyhow!(
+                "email_username and a password have to be set together!"
+            ))
+        }
+    };
+    Ok(builder.build())
+}
 
 pub enum MailBody<'a> {
     Raw(String),
     Template(&'a str, &'a Context),
 }
 
+pub struct MailAttachment {
+    pub filename: String,
+    pub content_type: String,
+    pub content: Vec<u8>,
+}
+
+impl MailAttachment {
+    pub fn calendar(event: &ics::Event, method: ics::Method) -> Self {
+        MailAttachment {
+            filename: String::from("audition.ics"),
+            content_type: method.content_type(),
+            content: ics::render(event, method).into_bytes(),
+        }
+    }
+}
+
+/// The parts of a booking which are shown in the candidate's calendar.
+pub struct CalendarBooking<'a> {
+    pub token: &'a str,
+    pub from_date: DateTime<Local>,
+    pub to_date: DateTime<Local>,
+    pub room_number: &'a str,
+    /// The display name of the date type in the language of the candidate.
+    pub date_type: &'a str,
+    pub description: &'a str,
+}
+
+impl CalendarBooking<'_> {
+    pub fn attachment(
+        &self,
+        config: &Config,
+        email: &str,
+        lang: &str,
+        method: ics::Method,
+    ) -> Result<MailAttachment> {
+        let args: HashMap<&str, FluentValue> = hash_map! { "datetype" => self.date_type.into() };
+        let summary = LOCALES
+            .lookup_single_language(&lang.parse()?, "calendar-summary", Some(&args))
+            .ok_or_else(|| anyhow!("Missing translation for calendar-summary!"))?
+            .replace(['\u{2068}', '\u{2069}'], "");
+        let organizer: Mailbox = config.email_from_address.parse()?;
+        Ok(MailAttachment::calendar(
+            &ics::Event {
+                uid: ics::uid(self.token, &config.web_address),
+                start: self.from_date,
+                end: self.to_date,
+                summary: &summary,
+                location: self.room_number,
+                description: self.description,
+                organizer: organizer.email.as_ref(),
+                attendee: email,
+                tentative: false,
+            },
+            method,
+        ))
+    }
+}
+
+/// Delivery of a mail is given up after this many failed attempts.
+const MAX_ATTEMPTS: i32 = 10;
+/// How long a mail is reserved for the worker delivering it.
+const LOCK_SECONDS: i32 = 10 * 60;
+/// The longest time between two delivery attempts.
+const MAX_BACKOFF_SECONDS: i32 = 6 * 60 * 60;
+
+/// Renders a mail and adds it to the outbox, from which it is delivered in the background.
+///
+/// Besides the plain text, every mail gets an HTML part which shows the text in the common
+/// layout. If the template context contains an `unsubscribe` link, it is added to the footer.
+///
+/// If `db` is inside a transaction, the mail is only sent if the transaction is committed.
 pub async fn send_mail(
+    db: &mut PgConnection,
+    config: &Config,
+    to: &str,
+    lang: &str,
+    subject: &str,
+    subject_args: Option<&HashMap<&str, &str>>,
+    body: MailBody<'_>,
+) -> Result<()> {
+    send_mail_with_attachments(
+        db,
+        config,
+        to,
+        lang,
+        subject,
+        subject_args,
+        body,
+        Vec::new(),
+    )
+    .await
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn send_mail_with_attachments(
+    db: &mut PgConnection,
     config: &Config,
-    mailer: &Mailer,
     to: &str,
     lang: &str,
     subject: &str,
     subject_args: Option<&HashMap<&str, &str>>,
     body: MailBody<'_>,
+    attachments: Vec<MailAttachment>,
 ) -> Result<()> {
     let subject_args = match subject_args {
         Some(args) => {
@@ -46,32 +280,147 @@ pub async fn send_mail(
         }
         None => None,
     };
-    let message_builder = Message::builder()
-        .to(to.parse()?)
-        .from(config.email_from_address.parse()?)
-        .header(header::ContentType::TEXT_PLAIN)
-        .subject(
-            LOCALES
-                .lookup_single_language(&lang.parse()?, subject, subject_args.as_ref())
-                .ok_or_else(|| anyhow!(format!("Missing translation for {}!", subject)))?,
-        );
-    let message = match body {
-        MailBody::Raw(text) => message_builder.body(text),
-        MailBody::Template(key, context) => message_builder.body(
-            MAIL_TEMPLATES
-                .render(key, context)?
-                .into_body(Some(ContentTransferEncoding::Base64)),
+    // Invalid addresses would only be noticed on delivery otherwise.
+    to.parse::<Mailbox>()?;
+    let subject = LOCALES
+        .lookup_single_language(&lang.parse()?, subject, subject_args.as_ref())
+        .ok_or_else(|| anyhow!(format!("Missing translation for {}!", subject)))?;
+    let (body, unsubscribe) = match body {
+        MailBody::Raw(text) => (text, None),
+        MailBody::Template(key, context) => (
+            MAIL_TEMPLATES.render(key, context)?,
+            context
+                .get("unsubscribe")
+                .and_then(|unsubscribe| unsubscribe.as_str())
+                .map(String::from),
         ),
-    }?;
-    mailer.send(message).await?;
+    };
+    let paragraphs = body
+        .split("\n\n")
+        .map(|paragraph| paragraph.lines().map(str::trim).collect::<Vec<_>>())
+        .filter(|lines| lines.iter().any(|line| !line.is_empty()))
+        .collect::<Vec<_>>();
+    let html = MAIL_TEMPLATES.render(
+        "layout.html.tera",
+        &Context::from_serialize(context! {
+            lang,
+            subject: &subject,
+            paragraphs,
+            web_address: &config.web_address,
+            unsubscribe,
+        })?,
+    )?;
+    let id = sqlx::query_scalar!(
+        "insert into mail_outbox (recipient, subject, body, html) values ($1, $2, $3, $4) returning id",
+        to,
+        subject,
+        body,
+        html
+    )
+    .fetch_one(&mut *db)
+    .await?;
+    for attachment in attachments {
+        sqlx::query!(
+            "insert into mail_attachments (mail, filename, content_type, content) values ($1, $2, $3, $4)",
+            id,
+            attachment.filename,
+            attachment.content_type,
+            attachment.content
+        )
+        .execute(&mut *db)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Sends all mails from the outbox which are due, retrying failed ones with an exponential backoff.
+pub async fn deliver_mails(db: &PgPool, config: &Config, mailer: &Mailer) -> Result<()> {
+    let from: Mailbox = config.email_from_address.parse()?;
+    // Mails reserved by other workers are skipped instead of waited for.
+    while let Some(mail) = sqlx::query!(
+        r#"update mail_outbox set next_attempt = now() + make_interval(secs => $1), attempts = attempts + 1
+        where id = (
+            select id from mail_outbox
+            where next_attempt <= now()
+            order by next_attempt
+            limit 1
+            for update skip locked
+        )
+        returning id, recipient, subject, body, html, attempts"#,
+        LOCK_SECONDS as f64
+    )
+    .fetch_optional(db)
+    .await?
+    {
+        let result = async {
+            let attachments = sqlx::query!(
+                "select filename, content_type, content from mail_attachments where mail = $1 order by id",
+                mail.id
+            )
+            .fetch_all(db)
+            .await?;
+            let builder = Message::builder()
+                .to(mail.recipient.parse()?)
+                .from(from.clone())
+                .subject(mail.subject);
+            // Mails queued before HTML parts were introduced only have the plain text.
+            let message = match (mail.html, attachments.is_empty()) {
+                (Some(html), true) => {
+                    builder.multipart(MultiPart::alternative_plain_html(mail.body, html))?
+                }
+                (Some(html), false) => {
+                    let mut multipart = MultiPart::mixed()
+                        .multipart(MultiPart::alternative_plain_html(mail.body, html));
+                    for attachment in attachments {
+                        multipart = multipart.singlepart(
+                            Attachment::new(attachment.filename).body(
+                                attachment.content,
+                                header::ContentType::parse(&attachment.content_type)?,
+                            ),
+                        );
+                    }
+                    builder.multipart(multipart)?
+                }
+                (None, _) => builder
+                    .header(header::ContentType::TEXT_PLAIN)
+                    .body(mail.body.into_body(Some(ContentTransferEncoding::Base64)))?,
+            };
+            mailer.send(message).await?;
+            Ok::<_, anyhow::Error>(())
+        }
+        .await;
+        match result {
+            Ok(()) => {
+                sqlx::query!("delete from mail_outbox where id = $1", mail.id)
+                    .execute(db)
+                    .await?;
+            }
+            Err(error) => {
+                error!("Could not deliver mail {}: {:?}", mail.id, error);
+                sqlx::query!(
+                    "update mail_outbox set \
+                    next_attempt = case when attempts < $2 \
+                        then now() + make_interval(secs => least(60 * power(2, attempts - 1), $3)) \
+                    end, \
+                    last_error = $4 \
+                    where id = $1",
+                    mail.id,
+                    MAX_ATTEMPTS,
+                    MAX_BACKOFF_SECONDS as f64,
+                    error.to_string()
+                )
+                .execute(db)
+                .await?;
+            }
+        }
+    }
     Ok(())
 }
 
 pub async fn waiting_list_notify(
-    db: &mut Connection<Database>,
+    db: &mut PgConnection,
     date_type: &str,
     config: &Config,
-    mailer: &Mailer,
 ) -> Result<()> {
     let recipients: Vec<(String, String, String)> = sqlx::query!(
         r#"select email, lang, token
@@ -79,14 +428,14 @@ pub async fn waiting_list_notify(
         where date_type = $1"#,
         &date_type
     )
-    .fetch(&mut **db)
+    .fetch(&mut *db)
     .map_ok(|record| (record.email, record.lang, record.token))
     .try_collect::<Vec<(String, String, String)>>()
     .await?;
 
     for (email, lang, token) in recipients {
-        let ignore_deadline = check_date_type_access(date_type, Some(&token), config, db).await?;
-        if Date::get_available_dates(db, date_type, config, None, ignore_deadline)
+        let ignore_deadline = check_date_type_access(date_type, Some(&token), db).await?;
+        if Date::get_available_dates(db, date_type, None, ignore_deadline)
             .await?
             .is_empty()
         {
@@ -98,8 +447,8 @@ pub async fn waiting_list_notify(
             "datetype" => date_type.display_name.as_deref().unwrap()
         };
         send_mail(
+            db,
             config,
-            mailer,
             &email,
             &lang,
             "waiting-list",
@@ -120,3 +469,96 @@ pub async fn waiting_list_notify(
     }
     Ok(())
 }
+
+/// Notifies the waiting lists about all dates which have been published since the last call.
+pub async fn notify_published_dates(db: &mut PgConnection, config: &Config) -> Result<()> {
+    let mut tx = db.begin().await?;
+    let date_types = sqlx::query_scalar!(
+        "update dates set waiting_list_notified = true \
+        where not waiting_list_notified and publish_at <= now() \
+        returning date_type"
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    for date_type in date_types.into_iter().unique() {
+        waiting_list_notify(&mut tx, &date_type, config).await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Reminds candidates of their booked dates at the hours configured for the date type.
+///
+/// Reminders which were already due when the booking was made are skipped, and if several
+/// reminders are due at once, only one mail is sent.
+pub async fn send_reminders(db: &mut PgConnection, config: &Config) -> Result<()> {
+    let reminders = sqlx::query!(
+        r#"select
+            bookings.token,
+            bookings.email,
+            bookings.lang,
+            dates.from_date,
+            dates.to_date,
+            dates.date_type,
+            rooms.room_number,
+            array_agg(reminder_hours.hours) as "hours!"
+        from bookings
+        join dates on dates.id = bookings.date_id
+        join rooms on rooms.id = dates.room_id
+        join date_types on date_types.id = dates.date_type
+        cross join unnest(date_types.reminder_hours) as reminder_hours (hours)
+        where dates.from_date > now()
+        and dates.from_date - make_interval(hours => reminder_hours.hours) <= now()
+        and bookings.created < dates.from_date - make_interval(hours => reminder_hours.hours)
+        and not exists (
+            select 1 from booking_reminders
+            where booking_reminders.booking = bookings.token and booking_reminders.hours = reminder_hours.hours
+        )
+        group by bookings.token, dates.id, rooms.room_number"#
+    )
+    .fetch_all(&mut *db)
+    .await?;
+
+    for reminder in reminders {
+        let from_date = reminder.from_date.with_timezone(&Local);
+        let to_date = reminder.to_date.with_timezone(&Local);
+        // The reminders are claimed before the mail is queued, so a concurrent run which found
+        // the same reminders doesn't send them again.
+        let mut tx = db.begin().await?;
+        let claimed = sqlx::query_scalar!(
+            "insert into booking_reminders (booking, hours) select $1, unnest($2::integer[]) \
+            on conflict do nothing returning hours",
+            &reminder.token,
+            &reminder.hours,
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        if claimed.is_empty() {
+            continue;
+        }
+        let announcement = get_announcement(&reminder.date_type, &reminder.lang, &mut tx).await?;
+        send_mail(
+            &mut tx,
+            config,
+            &reminder.email,
+            &reminder.lang,
+            "mail-reminder-subject",
+            None,
+            MailBody::Template(
+                "reminder.tera",
+                &Context::from_serialize(context! {
+                    lang: &reminder.lang,
+                    link: format!("{}/booking/delete/{}", &config.web_address, &reminder.token),
+                    day: format!("{}", from_date.naive_local().format("%d.%m.%Y")),
+                    from: format!("{}", from_date.naive_local().format("%H:%M")),
+                    to: format!("{}", to_date.naive_local().format("%H:%M")),
+                    room_number: &reminder.room_number,
+                    announcement: &announcement,
+                })?,
+            ),
+        )
+        .await?;
+        tx.commit().await?;
+    }
+    Ok(())
+}
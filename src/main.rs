@@ -1,8 +1,17 @@
 mod admin;
+mod audit;
 mod auth;
+mod csrf;
+mod csv;
+mod ics;
+mod jobs;
 mod language;
 mod mail;
 mod model;
+mod rate_limit;
+#[cfg(test)]
+mod tests;
+mod two_factor;
 mod user;
 mod util;
 
@@ -12,19 +21,19 @@ extern crate rocket;
 #[macro_use]
 extern crate lazy_static;
 
-use std::{
-    collections::{BTreeMap, HashMap},
-    sync::atomic::{AtomicBool, Ordering},
-};
+use std::collections::HashMap;
+use std::path::PathBuf;
 
 use chrono::{DateTime, Local};
 use itertools::Itertools;
-use lettre::{AsyncSmtpTransport, Tokio1Executor};
+use mail::{SmtpTls, Transport};
 use rocket::{
-    fairing::{self, AdHoc, Fairing},
+    fairing::AdHoc,
+    figment::Figment,
     fs::FileServer,
-    request::{FromRequest, Request},
+    request::Request,
     response::{self, Redirect, Responder},
+    tokio, Build, Rocket,
 };
 use rocket_db_pools::{sqlx, Database as DatabaseTrait};
 use rocket_dyn_templates::Template;
@@ -33,7 +42,7 @@ use sqlx::migrate::Migrator;
 use tera::Tera;
 use util::datetime_to_day;
 
-pub type Mailer = AsyncSmtpTransport<Tokio1Executor>;
+pub use mail::Mailer;
 
 pub type RocketResult<T = ()> = std::result::Result<T, RocketError>;
 pub struct RocketError(pub anyhow::Error);
@@ -59,48 +68,28 @@ pub struct Database(sqlx::PgPool);
 
 static MIGRATOR: Migrator = sqlx::migrate!();
 
-pub struct MigrationFairing(AtomicBool);
-
-impl MigrationFairing {
-    pub fn new() -> Self {
-        MigrationFairing(AtomicBool::new(false))
-    }
-}
-
-impl Default for MigrationFairing {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
-#[rocket::async_trait]
-impl Fairing for MigrationFairing {
-    fn info(&self) -> fairing::Info {
-        fairing::Info {
-            name: "Database Migration",
-            kind: fairing::Kind::Request,
-        }
-    }
-
-    async fn on_request(&self, request: &mut Request<'_>, _: &mut rocket::Data<'_>) {
-        if !self.0.swap(true, Ordering::SeqCst) {
-            let db = <&Database>::from_request(request).await.unwrap();
-            MIGRATOR.run(&db.0).await.unwrap();
-        }
-    }
-}
-
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone)]
 pub struct Config {
+    #[serde(default)]
+    email_transport: Transport,
+    email_directory: Option<PathBuf>,
     email_host: String,
     email_port: u16,
+    #[serde(default)]
+    email_tls: SmtpTls,
+    email_username: Option<String>,
+    email_password: Option<String>,
+    /// Alternative to `email_password` for secrets which are mounted as files.
+    email_password_file: Option<PathBuf>,
     email_from_address: String,
     web_address: String,
-    dates_per_day: usize,
-    days_deadline: u32,
     impressum: String,
-    enabled_date_types: Vec<String>,
-    application_deadlines: BTreeMap<String, String>,
+    invite_lifetime_hours: i32,
+    password_reset_lifetime_hours: i32,
+    /// Keeps the scheduler from running jobs in the background, for tests which run them on their
+    /// own.
+    #[serde(default)]
+    disable_jobs: bool,
 }
 
 lazy_static! {
@@ -112,6 +101,8 @@ lazy_static! {
                 ::std::process::exit(1);
             }
         };
+        // Only the HTML layout needs escaping, the other templates render plain text.
+        tera.autoescape_on(vec![".html.tera"]);
         tera.register_function("fluent", language::make_fluent_loader());
         tera
     };
@@ -193,9 +184,17 @@ pub async fn favicon_get() -> Redirect {
     Redirect::to("/static/favicon/favicon.ico")
 }
 
-#[rocket::main]
-async fn main() -> Result<(), rocket::Error> {
-    let rocket = rocket::build()
+/// Settings which have moved from the configuration to the booking settings of each date type.
+const MOVED_SETTINGS: [&str; 4] = [
+    "enabled_date_types",
+    "application_deadlines",
+    "dates_per_day",
+    "days_deadline",
+];
+
+/// Builds the application from `figment`, so tests can change the configuration.
+pub fn rocket(figment: Figment) -> Rocket<Build> {
+    let rocket = rocket::custom(figment)
         .attach(Template::custom(|engines| {
             engines
                 .tera
@@ -212,8 +211,81 @@ async fn main() -> Result<(), rocket::Error> {
         }))
         .attach(Database::init())
         .attach(AdHoc::config::<Config>())
-        .attach(MigrationFairing::new())
-        .register("/", catchers![auth::unauthorized_handler])
+        .attach(AdHoc::try_on_ignite("Moved Settings", |rocket| async {
+            // They would be ignored otherwise, so the deployment would fall back to the defaults
+            // of the migration without notice.
+            let moved: Vec<_> = MOVED_SETTINGS
+                .into_iter()
+                .filter(|key| rocket.figment().find_value(key).is_ok())
+                .collect();
+            if moved.is_empty() {
+                return Ok(rocket);
+            }
+            error!(
+                "{} can no longer be configured! Enter the values on the booking settings page \
+                at /admin/date-type-settings and remove them from the configuration.",
+                moved.join(", ")
+            );
+            Err(rocket)
+        }))
+        .attach(AdHoc::try_on_ignite(
+            "Database Migrations",
+            |rocket| async {
+                let db = match Database::fetch(&rocket) {
+                    Some(db) => db,
+                    None => return Err(rocket),
+                };
+                match MIGRATOR.run(&db.0).await {
+                    Ok(()) => Ok(rocket),
+                    Err(error) => {
+                        error!("Could not run database migrations: {}", error);
+                        Err(rocket)
+                    }
+                }
+            },
+        ))
+        .attach(AdHoc::on_liftoff("Job Scheduler", |rocket| {
+            Box::pin(async move {
+                if rocket.state::<Config>().unwrap().disable_jobs {
+                    return;
+                }
+                let scheduler = jobs::Scheduler::new(
+                    Database::fetch(rocket).unwrap().0.clone(),
+                    rocket.state::<Config>().unwrap().clone(),
+                    rocket.state::<Mailer>().unwrap().clone(),
+                );
+                if let Err(error) = scheduler.spawn().await {
+                    error!("Could not start the job scheduler: {:?}", error);
+                }
+            })
+        }))
+        .attach(AdHoc::on_liftoff("Mail Transport Check", |rocket| {
+            Box::pin(async move {
+                let config = rocket.state::<Config>().unwrap();
+                let mailer = rocket.state::<Mailer>().unwrap().clone();
+                let target = mailer.describe(config);
+                // Mails are queued anyway, so an unreachable server must not delay the launch.
+                tokio::spawn(async move {
+                    match mailer.test_connection().await {
+                        Ok(true) => info!("Connected to the {}.", target),
+                        Ok(false) => error!("The {} did not accept the connection!", target),
+                        Err(error) => error!(
+                            "Could not connect to the {}, mails will not be sent: {}",
+                            target, error
+                        ),
+                    }
+                });
+            })
+        }))
+        .register(
+            "/",
+            catchers![
+                auth::unauthorized_handler,
+                auth::forbidden_handler,
+                auth::two_factor_missing_handler,
+                user::too_many_requests_handler
+            ],
+        )
         .register("/booking", catchers![user::date_gone_handler])
         .mount("/static", FileServer::from("static/"))
         .mount("/", routes![favicon_get])
@@ -231,21 +303,53 @@ async fn main() -> Result<(), rocket::Error> {
                 auth::password_reset_get,
                 auth::password_reset_post,
                 auth::logout,
+                auth::sessions_get,
+                auth::sessions_post,
+                auth::admins_get,
+                auth::admins_post,
+                auth::profile_get,
+                auth::profile_post,
+                auth::password_change_post,
+                auth::calendar_post,
+                two_factor::login_two_factor_get,
+                two_factor::login_two_factor_post,
+                two_factor::two_factor_get,
+                two_factor::two_factor_post,
             ],
         )
         .mount(
             "/",
             routes![
                 admin::dashboard,
+                admin::calendar_feed,
+                admin::export_get,
+                admin::export_csv_get,
                 admin::date_new_1_get,
                 admin::date_new_1_post,
                 admin::date_new_2_post,
+                admin::schedule_templates_get,
+                admin::schedule_templates_post,
+                admin::date_import_get,
+                admin::date_import_post,
                 admin::room_manage_get,
                 admin::room_manage_post,
                 admin::announcements_get,
                 admin::announcements_post,
+                admin::date_types_get,
+                admin::date_types_post,
+                admin::date_type_get,
+                admin::date_type_post,
+                admin::date_type_settings_get,
+                admin::date_type_settings_post,
                 admin::date_cancel_get,
                 admin::date_cancel_post,
+                admin::booking_result_get,
+                admin::booking_result_post,
+                admin::result_mail_get,
+                admin::result_mail_post,
+                admin::audit_log_get,
+                admin::outbox_get,
+                admin::outbox_post,
             ],
         )
         .mount(
@@ -266,13 +370,18 @@ async fn main() -> Result<(), rocket::Error> {
 
     let config: Config = rocket.figment().extract().expect("config");
 
-    let mailer: Mailer = Mailer::builder_dangerous(config.email_host)
-        .port(config.email_port)
-        .build();
+    let mailer = Mailer::new(&config).expect("mail transport configuration");
 
-    let rocket = rocket.manage(mailer);
+    rocket.manage(mailer)
+}
 
-    rocket.ignite().await?.launch().await?;
+#[rocket::main]
+async fn main() -> Result<(), rocket::Error> {
+    rocket(rocket::Config::figment())
+        .ignite()
+        .await?
+        .launch()
+        .await?;
 
     Ok(())
 }
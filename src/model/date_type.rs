@@ -3,14 +3,11 @@ use anyhow::Result;
 use chrono::DateTime;
 use chrono::Duration;
 use chrono::Local;
-use chrono::NaiveDateTime;
-use rocket_db_pools::Connection;
 use serde::Deserialize;
 use serde::Serialize;
+use sqlx::PgConnection;
 
 use crate::util::datetime_to_day;
-use crate::Config;
-use crate::Database;
 
 use super::check_date_type_access;
 
@@ -20,6 +17,18 @@ pub struct Voice {
     pub display_name: Option<String>,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize, FromFormField, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "voice_positions", rename_all = "lowercase")]
+pub enum VoicePosition {
+    Booking,
+    Result,
+}
+
+impl VoicePosition {
+    pub const ALL: [VoicePosition; 2] = [VoicePosition::Booking, VoicePosition::Result];
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct DateType {
     pub value: String,
@@ -27,11 +36,7 @@ pub struct DateType {
 }
 
 impl DateType {
-    pub async fn get_by_value(
-        db: &mut Connection<Database>,
-        value: &str,
-        lang: &str,
-    ) -> Result<Self> {
+    pub async fn get_by_value(db: &mut PgConnection, value: &str, lang: &str) -> Result<Self> {
         let display_name = sqlx::query_scalar!(
             r#"select display_name
             from date_types_translations
@@ -39,7 +44,7 @@ impl DateType {
             &value,
             &lang
         )
-        .fetch_one(&mut **db)
+        .fetch_one(&mut *db)
         .await?;
         Ok(Self {
             value: String::from(value),
@@ -47,15 +52,16 @@ impl DateType {
         })
     }
 
-    pub async fn get_variants(db: &mut Connection<Database>, lang: &str) -> Result<Vec<Self>> {
+    pub async fn get_variants(db: &mut PgConnection, lang: &str) -> Result<Vec<Self>> {
         Ok(sqlx::query!(
             r#"select id, display_name
             from date_types
             join date_types_translations on date_types.id = date_types_translations.date_type
-            where lang = $1"#,
+            where lang = $1
+            order by sort_order, id"#,
             &lang
         )
-        .fetch_all(&mut **db)
+        .fetch_all(&mut *db)
         .await?
         .into_iter()
         .map(|record| Self {
@@ -67,7 +73,7 @@ impl DateType {
 
     pub async fn get_voices(
         &self,
-        db: &mut Connection<Database>,
+        db: &mut PgConnection,
         lang: &str,
         position: &str,
     ) -> Result<Vec<Voice>> {
@@ -77,12 +83,13 @@ impl DateType {
         join voices_translations on voices.id = voices_translations.voice \
         where lang = $1 \
         and position::text = $2 \
-        and date_type = $3",
+        and date_type = $3 \
+        order by sort_order, id",
             &lang,
             &position,
             &self.value,
         )
-        .fetch_all(&mut **db)
+        .fetch_all(&mut *db)
         .await?
         .into_iter()
         .map(|record| Voice {
@@ -93,6 +100,56 @@ impl DateType {
     }
 }
 
+/// Booking settings of a date type which can be changed by the admins.
+#[derive(Serialize)]
+pub struct DateTypeSettings {
+    pub enabled: bool,
+    pub opens_at: Option<DateTime<Local>>,
+    pub application_deadline: Option<DateTime<Local>>,
+    /// Number of days before a date after which it can no longer be booked.
+    pub days_deadline: i32,
+    /// Maximum number of dates offered per day, `0` meaning no limit.
+    pub dates_per_day: i32,
+    /// Hours before a date at which the candidate is reminded of it.
+    pub reminder_hours: Vec<i32>,
+}
+
+impl DateTypeSettings {
+    pub async fn get(db: &mut PgConnection, date_type: &str) -> Result<Self> {
+        let record = sqlx::query!(
+            r#"select enabled, opens_at, application_deadline, days_deadline, dates_per_day, reminder_hours
+            from date_types
+            where id = $1"#,
+            &date_type
+        )
+        .fetch_one(&mut *db)
+        .await?;
+        Ok(Self {
+            enabled: record.enabled,
+            opens_at: record
+                .opens_at
+                .map(|opens_at| opens_at.with_timezone(&Local)),
+            application_deadline: record
+                .application_deadline
+                .map(|deadline| deadline.with_timezone(&Local)),
+            days_deadline: record.days_deadline,
+            dates_per_day: record.dates_per_day,
+            reminder_hours: record.reminder_hours,
+        })
+    }
+
+    /// Whether dates can currently be booked, optionally ignoring the application deadline.
+    pub fn is_open(&self, ignore_deadline: bool) -> bool {
+        let now = Local::now();
+        self.enabled
+            && self.opens_at.is_none_or(|opens_at| now >= opens_at)
+            && (ignore_deadline
+                || self
+                    .application_deadline
+                    .is_none_or(|deadline| now < deadline))
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct Date {
     pub id: i32,
@@ -104,21 +161,14 @@ pub struct Date {
 
 impl Date {
     pub async fn get_available_dates(
-        db: &mut Connection<Database>,
+        db: &mut PgConnection,
         date_type: &str,
-        config: &Config,
         lang: Option<&str>,
         ignore_deadline: bool,
     ) -> Result<Vec<Date>> {
-        if !ignore_deadline {
-            if let Some(deadline) = config.application_deadlines.get(date_type) {
-                let deadline =
-                    NaiveDateTime::parse_from_str(deadline, crate::BROWSER_DATETIME_FORMAT)?;
-                let now = Local::now().naive_local();
-                if now >= deadline {
-                    return Ok(Vec::new());
-                }
-            }
+        let settings = DateTypeSettings::get(db, date_type).await?;
+        if !settings.is_open(ignore_deadline) {
+            return Ok(Vec::new());
         }
 
         let mut dates: Vec<Date> = match lang {
@@ -130,12 +180,13 @@ impl Date {
                     join date_types_translations on date_types_translations.date_type = dates.date_type \
                     where token is null \
                     and dates.date_type = $1 \
+                    and publish_at <= now() \
                     and date_types_translations.lang = $2 \
                     order by from_date asc",
                 &date_type,
                 &lang,
             )
-            .fetch_all(&mut **db)
+            .fetch_all(&mut *db)
             .await?
             .into_iter()
             .map(|record| Date {
@@ -157,10 +208,11 @@ impl Date {
                     left join bookings on dates.id = bookings.date_id \
                     where token is null \
                     and dates.date_type = $1 \
+                    and publish_at <= now() \
                     order by from_date asc",
                 &date_type,
             )
-            .fetch_all(&mut **db)
+            .fetch_all(&mut *db)
             .await?
             .into_iter()
             .map(|record| Date {
@@ -176,18 +228,18 @@ impl Date {
             .collect(),
         };
 
-        if config.days_deadline > 0 {
+        if settings.days_deadline > 0 {
             let today = datetime_to_day(Local::now());
             dates.retain(|date| {
                 datetime_to_day(date.from_date)
-                    >= today + Duration::days(config.days_deadline as i64)
+                    >= today + Duration::days(settings.days_deadline as i64)
             });
         } else {
             let now = Local::now();
             dates.retain(|date| date.from_date >= now);
         }
 
-        if dates.is_empty() || config.dates_per_day == 0 {
+        if dates.is_empty() || settings.dates_per_day == 0 {
             return Ok(dates);
         }
 
@@ -197,7 +249,7 @@ impl Date {
         while i < dates.len() {
             let next_day = datetime_to_day(dates[i].from_date);
             if current_day == next_day {
-                if current_count < config.dates_per_day {
+                if current_count < settings.dates_per_day {
                     current_count += 1;
                     i += 1;
                 } else {
@@ -214,18 +266,17 @@ impl Date {
     }
 
     pub async fn get_available_date(
-        db: &mut Connection<Database>,
+        db: &mut PgConnection,
         id: i32,
         lang: &str,
-        config: &Config,
         token: Option<&str>,
     ) -> Result<Option<Date>> {
         let date_type = sqlx::query_scalar!("select date_type from dates where id = $1", &id)
-            .fetch_one(&mut **db)
+            .fetch_one(&mut *db)
             .await?;
-        let ignore_deadline = check_date_type_access(&date_type, token, config, db).await?;
+        let ignore_deadline = check_date_type_access(&date_type, token, db).await?;
         let mut dates =
-            Self::get_available_dates(db, &date_type, config, Some(lang), ignore_deadline).await?;
+            Self::get_available_dates(db, &date_type, Some(lang), ignore_deadline).await?;
         dates.retain(|date| date.id == id);
         match dates.len() {
             0 => Ok(None),
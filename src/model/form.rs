@@ -1,4 +1,4 @@
-use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
 use lazy_regex::regex_is_match;
 use rocket::form::error::ErrorKind;
 use rocket::form::FromFormField;
@@ -118,6 +118,22 @@ impl<'r> FromFormField<'r> for FormDateTime {
     }
 }
 
+pub struct FormDate(NaiveDate);
+
+impl IntoInner<NaiveDate> for FormDate {
+    fn into_inner(self) -> NaiveDate {
+        self.0
+    }
+}
+
+impl<'r> FromFormField<'r> for FormDate {
+    fn from_value(field: ValueField<'r>) -> form::Result<'r, Self> {
+        NaiveDate::parse_from_str(field.value, "%Y-%m-%d")
+            .map(FormDate)
+            .map_err(|_| form::Error::validation("validation-date").into())
+    }
+}
+
 pub struct SelectString<'r>(pub &'r str);
 
 impl<'r> IntoInner<&'r str> for SelectString<'r> {
@@ -1,15 +1,17 @@
 pub mod date_type;
 pub mod form;
+pub mod schedule;
 
 use anyhow::Result;
-use chrono::{Local, NaiveDateTime};
 use rocket_db_pools::Connection;
 use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
 
-use crate::{Config, Database};
+use crate::Database;
 
 pub use date_type::*;
 pub use form::*;
+pub use schedule::*;
 
 #[derive(Serialize, Deserialize)]
 pub struct Message {
@@ -49,15 +51,15 @@ pub async fn validate_room<'a>(
 pub async fn get_announcement(
     position: &str,
     lang: &str,
-    db: &mut Connection<Database>,
+    db: &mut PgConnection,
 ) -> anyhow::Result<String> {
     let content = sqlx::query_scalar!(
         r#"select content from announcements
-        where position = ($1::text)::announcement_position and lang = ($2::text)::language"#,
+        where position = $1 and lang = ($2::text)::language"#,
         &position,
         &lang,
     )
-    .fetch_one(&mut **db)
+    .fetch_one(&mut *db)
     .await?;
     Ok(content)
 }
@@ -71,15 +73,13 @@ pub struct Room {
 pub async fn check_date_type_access(
     date_type: &str,
     token: Option<&str>,
-    config: &Config,
-    db: &mut Connection<Database>,
+    db: &mut PgConnection,
 ) -> Result<bool> {
-    let deadline = match config.application_deadlines.get(date_type) {
-        Some(deadline) => NaiveDateTime::parse_from_str(deadline, crate::BROWSER_DATETIME_FORMAT)
-            .unwrap()
-            .and_local_timezone(Local)
-            .earliest()
-            .unwrap(),
+    let deadline = match DateTypeSettings::get(db, date_type)
+        .await?
+        .application_deadline
+    {
+        Some(deadline) => deadline,
         None => return Ok(true),
     };
 
@@ -89,7 +89,7 @@ pub async fn check_date_type_access(
                 r#"select entered from waiting_list where token = $1"#,
                 &token
             )
-            .fetch_optional(&mut **db)
+            .fetch_optional(&mut *db)
             .await?;
 
             match entered {
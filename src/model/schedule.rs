@@ -0,0 +1,260 @@
+use std::fmt;
+
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveTime};
+use itertools::Itertools;
+use serde::Serialize;
+use sqlx::PgConnection;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct TimeBlock {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+/// The times of a day in which dates are offered, like `10:00-13:00, 14:00-18:00`. The gaps
+/// between the blocks are breaks.
+pub struct TimeBlocks(pub Vec<TimeBlock>);
+
+impl TimeBlocks {
+    /// Parses blocks separated by commas, semicolons or line breaks, which have to be in order and
+    /// must not overlap.
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut blocks: Vec<TimeBlock> = Vec::new();
+        for block in value
+            .split([',', ';', '\n'])
+            .map(str::trim)
+            .filter(|block| !block.is_empty())
+        {
+            let (start, end) = block.split_once('-')?;
+            let block = TimeBlock {
+                start: NaiveTime::parse_from_str(start.trim(), "%H:%M").ok()?,
+                end: NaiveTime::parse_from_str(end.trim(), "%H:%M").ok()?,
+            };
+            if block.start >= block.end || blocks.last().is_some_and(|last| last.end > block.start)
+            {
+                return None;
+            }
+            blocks.push(block);
+        }
+        if blocks.is_empty() {
+            None
+        } else {
+            Some(TimeBlocks(blocks))
+        }
+    }
+}
+
+impl fmt::Display for TimeBlocks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let blocks = self.0.iter().format_with(", ", |block, f| {
+            f(&format_args!(
+                "{}-{}",
+                block.start.format("%H:%M"),
+                block.end.format("%H:%M")
+            ))
+        });
+        write!(f, "{}", blocks)
+    }
+}
+
+/// Slices the time blocks of every day into slots of `slot` minutes, with `buffer` minutes between
+/// two slots. A block ends with the last slot that fits into it.
+pub fn generate_slots(
+    days: impl IntoIterator<Item = NaiveDate>,
+    blocks: &TimeBlocks,
+    slot: Duration,
+    buffer: Duration,
+) -> Vec<(DateTime<Local>, DateTime<Local>)> {
+    let mut slots = Vec::new();
+    for day in days {
+        for block in &blocks.0 {
+            let end = day.and_time(block.end);
+            let mut start = day.and_time(block.start);
+            while start + slot <= end {
+                // Times which don't exist because of daylight saving time are left out.
+                if let (Some(from_date), Some(to_date)) = (
+                    start.and_local_timezone(Local).earliest(),
+                    (start + slot).and_local_timezone(Local).earliest(),
+                ) {
+                    slots.push((from_date, to_date));
+                }
+                start += slot + buffer;
+            }
+        }
+    }
+    slots
+}
+
+/// The days between `from_day` and `to_day` inclusively which fall on one of `weekdays`,
+/// numbered from Monday as 1 to Sunday as 7.
+pub fn days_between(
+    from_day: NaiveDate,
+    to_day: NaiveDate,
+    weekdays: &[u32],
+) -> impl Iterator<Item = NaiveDate> + '_ {
+    from_day
+        .iter_days()
+        .take_while(move |day| *day <= to_day)
+        .filter(|day| weekdays.contains(&day.weekday().number_from_monday()))
+}
+
+/// Saved time blocks and slot lengths which can be used again when creating dates.
+#[derive(Serialize)]
+pub struct ScheduleTemplate {
+    pub id: i32,
+    pub name: String,
+    /// The time blocks in the format of [`TimeBlocks`].
+    pub times: String,
+    pub slot_minutes: i32,
+    pub buffer_minutes: i32,
+}
+
+impl ScheduleTemplate {
+    pub async fn get_all(db: &mut PgConnection) -> Result<Vec<Self>> {
+        let templates = sqlx::query!(
+            "select id, name, slot_minutes, buffer_minutes from schedule_templates order by name"
+        )
+        .fetch_all(&mut *db)
+        .await?;
+        let blocks = sqlx::query!(
+            "select template, start_time, end_time from schedule_template_blocks \
+            order by template, start_time"
+        )
+        .fetch_all(&mut *db)
+        .await?;
+        Ok(templates
+            .into_iter()
+            .map(|record| Self {
+                id: record.id,
+                name: record.name,
+                times: TimeBlocks(
+                    blocks
+                        .iter()
+                        .filter(|block| block.template == record.id)
+                        .map(|block| TimeBlock {
+                            start: block.start_time,
+                            end: block.end_time,
+                        })
+                        .collect(),
+                )
+                .to_string(),
+                slot_minutes: record.slot_minutes,
+                buffer_minutes: record.buffer_minutes,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(value: &str) -> Option<String> {
+        TimeBlocks::parse(value).map(|blocks| blocks.to_string())
+    }
+
+    fn day(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, day).unwrap()
+    }
+
+    fn slots(times: &str, slot: i64, buffer: i64) -> Vec<String> {
+        generate_slots(
+            [day(14)],
+            &TimeBlocks::parse(times).unwrap(),
+            Duration::minutes(slot),
+            Duration::minutes(buffer),
+        )
+        .into_iter()
+        .map(|(from_date, to_date)| {
+            format!("{}-{}", from_date.format("%H:%M"), to_date.format("%H:%M"))
+        })
+        .collect()
+    }
+
+    #[test]
+    fn parses_blocks() {
+        assert_eq!(
+            parse("10:00-13:00, 14:00 - 18:00"),
+            Some(String::from("10:00-13:00, 14:00-18:00"))
+        );
+        assert_eq!(
+            parse("8:00-9:00;\n9:00-10:00\n"),
+            Some(String::from("08:00-09:00, 09:00-10:00"))
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_blocks() {
+        assert_eq!(parse(""), None);
+        assert_eq!(parse(" , ;"), None);
+        assert_eq!(parse("10:00"), None);
+        assert_eq!(parse("10:00-"), None);
+        assert_eq!(parse("10-12"), None);
+        assert_eq!(parse("10:00-24:00"), None);
+        assert_eq!(parse("10:00-12:00, foo"), None);
+    }
+
+    #[test]
+    fn rejects_blocks_crossing_midnight() {
+        assert_eq!(parse("22:00-01:00"), None);
+        assert_eq!(parse("10:00-10:00"), None);
+    }
+
+    #[test]
+    fn rejects_overlapping_blocks() {
+        assert_eq!(parse("10:00-13:00, 12:00-14:00"), None);
+        assert_eq!(parse("14:00-18:00, 10:00-13:00"), None);
+    }
+
+    #[test]
+    fn generates_slots_up_to_the_end_of_a_block() {
+        assert_eq!(
+            slots("10:00-11:00", 20, 0),
+            ["10:00-10:20", "10:20-10:40", "10:40-11:00"]
+        );
+        assert_eq!(slots("10:00-11:00", 60, 0), ["10:00-11:00"]);
+        assert!(slots("10:00-11:00", 61, 0).is_empty());
+    }
+
+    #[test]
+    fn generates_slots_with_buffers() {
+        assert_eq!(slots("10:00-11:00", 20, 10), ["10:00-10:20", "10:30-10:50"]);
+        // The buffer after the last slot of a block doesn't have to fit into it.
+        assert_eq!(slots("10:00-10:50", 20, 10), ["10:00-10:20", "10:30-10:50"]);
+        assert_eq!(
+            slots("10:00-10:45, 11:00-11:30", 30, 15),
+            ["10:00-10:30", "11:00-11:30"]
+        );
+    }
+
+    #[test]
+    fn generates_slots_on_every_day() {
+        let blocks = TimeBlocks::parse("10:00-10:30").unwrap();
+        let slots = generate_slots(
+            [day(14), day(15)],
+            &blocks,
+            Duration::minutes(30),
+            Duration::zero(),
+        );
+        assert_eq!(slots.len(), 2);
+        assert_eq!(slots[0].0.date_naive(), day(14));
+        assert_eq!(slots[1].0.date_naive(), day(15));
+    }
+
+    #[test]
+    fn finds_days_between() {
+        // 2026-10-12 is a Monday.
+        assert_eq!(
+            days_between(day(12), day(25), &[1, 3]).collect::<Vec<_>>(),
+            [day(12), day(14), day(19), day(21)]
+        );
+        assert_eq!(
+            days_between(day(12), day(18), &[1, 2, 3, 4, 5, 6, 7]).count(),
+            7
+        );
+        assert_eq!(days_between(day(12), day(12), &[1]).count(), 1);
+        assert_eq!(days_between(day(13), day(12), &[1, 2]).count(), 0);
+        assert_eq!(days_between(day(12), day(18), &[]).count(), 0);
+    }
+}
@@ -0,0 +1,263 @@
+use std::net::IpAddr;
+
+use rocket_db_pools::sqlx;
+use rocket_db_pools::Connection;
+use serde_json::json;
+use sqlx::Acquire;
+
+use crate::audit;
+use crate::Database;
+
+/// An action whose attempts are limited per client IP and per account.
+#[derive(Clone, Copy)]
+pub enum Action {
+    Login,
+    TwoFactor,
+    PasswordResetRequest,
+    Invite,
+    Booking,
+    WaitingList,
+}
+
+struct Limit {
+    max_attempts: i64,
+    window_minutes: i32,
+    lockout_minutes: i32,
+}
+
+impl Action {
+    const ALL: [Action; 6] = [
+        Action::Login,
+        Action::TwoFactor,
+        Action::PasswordResetRequest,
+        Action::Invite,
+        Action::Booking,
+        Action::WaitingList,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Action::Login => "login",
+            Action::TwoFactor => "two-factor",
+            Action::PasswordResetRequest => "password-reset-request",
+            Action::Invite => "invite",
+            Action::Booking => "booking",
+            Action::WaitingList => "waiting-list",
+        }
+    }
+
+    fn limit(self, key: &Key) -> Limit {
+        let (max_attempts, window_minutes, lockout_minutes) = match (self, key) {
+            (Action::Login, Key::Ip(_)) => (20, 15, 15),
+            (Action::Login, Key::Account(_)) => (5, 15, 15),
+            (Action::TwoFactor, Key::Ip(_)) => (20, 15, 15),
+            (Action::TwoFactor, Key::Account(_)) => (5, 15, 15),
+            (Action::PasswordResetRequest, Key::Ip(_)) => (10, 60, 60),
+            (Action::PasswordResetRequest, Key::Account(_)) => (3, 60, 60),
+            (Action::Invite, Key::Ip(_)) => (30, 60, 60),
+            (Action::Invite, Key::Account(_)) => (3, 60, 60),
+            (Action::Booking, Key::Ip(_)) => (20, 60, 60),
+            (Action::Booking, Key::Account(_)) => (5, 60, 60),
+            (Action::WaitingList, Key::Ip(_)) => (10, 60, 60),
+            (Action::WaitingList, Key::Account(_)) => (3, 60, 60),
+        };
+        Limit {
+            max_attempts,
+            window_minutes,
+            lockout_minutes,
+        }
+    }
+}
+
+/// Whom an attempt is attributed to. Accounts are identified by their email address.
+pub enum Key<'a> {
+    Ip(IpAddr),
+    Account(&'a str),
+}
+
+impl Key<'_> {
+    fn value(&self) -> String {
+        match self {
+            Key::Ip(ip) => format!("ip:{}", ip),
+            Key::Account(email) => format!("account:{}", email.to_lowercase()),
+        }
+    }
+}
+
+/// Collects the keys of a request. The client IP might be unknown, e.g. in local tests.
+pub fn keys<'a>(ip: Option<IpAddr>, account: &'a str) -> Vec<Key<'a>> {
+    let mut keys = vec![Key::Account(account)];
+    if let Some(ip) = ip {
+        keys.push(Key::Ip(ip));
+    }
+    keys
+}
+
+/// Whether any of the keys is currently locked out from the action.
+pub async fn is_locked(
+    db: &mut Connection<Database>,
+    action: Action,
+    keys: &[Key<'_>],
+) -> anyhow::Result<bool> {
+    let keys: Vec<String> = keys.iter().map(Key::value).collect();
+    Ok(sqlx::query_scalar!(
+        r#"select exists(
+            select 1 from rate_limit_lockouts
+            where action = $1 and key = any($2) and until > now()
+        ) as "locked!""#,
+        action.name(),
+        &keys,
+    )
+    .fetch_one(&mut **db)
+    .await?)
+}
+
+/// Records an attempt for all keys and locks out every key which exceeded its limit. Returns
+/// whether the attempt is within the limits of all keys.
+///
+/// Attempts of a key are recorded one after another, so parallel requests can't all slip through
+/// before the first of them is counted.
+async fn record(
+    db: &mut Connection<Database>,
+    action: Action,
+    keys: &[Key<'_>],
+) -> anyhow::Result<bool> {
+    let mut allowed = true;
+    for key in keys {
+        let limit = action.limit(key);
+        let value = key.value();
+        let mut tx = db.begin().await?;
+        sqlx::query!(
+            "select pg_advisory_xact_lock(hashtext($1 || ' ' || $2))",
+            action.name(),
+            &value,
+        )
+        .execute(&mut *tx)
+        .await?;
+        // Another request might have locked the key out while this one waited for the lock.
+        let locked = sqlx::query_scalar!(
+            r#"select exists(
+                select 1 from rate_limit_lockouts
+                where action = $1 and key = $2 and until > now()
+            ) as "locked!""#,
+            action.name(),
+            &value,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if locked {
+            allowed = false;
+            continue;
+        }
+        sqlx::query!(
+            "delete from rate_limit_attempts
+            where action = $1 and key = $2 and created < now() - make_interval(mins => $3)",
+            action.name(),
+            &value,
+            limit.window_minutes,
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "insert into rate_limit_attempts (action, key) values ($1, $2)",
+            action.name(),
+            &value,
+        )
+        .execute(&mut *tx)
+        .await?;
+        let attempts = sqlx::query_scalar!(
+            r#"select count(*) as "count!" from rate_limit_attempts where action = $1 and key = $2"#,
+            action.name(),
+            &value,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if attempts > limit.max_attempts {
+            allowed = false;
+            sqlx::query!(
+                "insert into rate_limit_lockouts (action, key, until)
+                values ($1, $2, now() + make_interval(mins => $3))
+                on conflict (action, key) do update set until = excluded.until",
+                action.name(),
+                &value,
+                limit.lockout_minutes,
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query!(
+                "delete from rate_limit_attempts where action = $1 and key = $2",
+                action.name(),
+                &value,
+            )
+            .execute(&mut *tx)
+            .await?;
+            audit::log(
+                &mut tx,
+                None,
+                "lockout",
+                &[value],
+                json!({ "action": action.name(), "minutes": limit.lockout_minutes }),
+            )
+            .await?;
+        }
+        tx.commit().await?;
+    }
+    Ok(allowed)
+}
+
+/// Records an attempt unless one of the keys is locked out. Returns whether the attempt may proceed.
+///
+/// Attempts are counted before they are checked, e.g. before a password is verified, so successful
+/// ones have to be cleared afterwards if they shouldn't count.
+pub async fn attempt(
+    db: &mut Connection<Database>,
+    action: Action,
+    keys: &[Key<'_>],
+) -> anyhow::Result<bool> {
+    if is_locked(db, action, keys).await? {
+        return Ok(false);
+    }
+    record(db, action, keys).await
+}
+
+/// Forgets the previous attempts of a key, e.g. after a successful login.
+pub async fn clear(
+    db: &mut Connection<Database>,
+    action: Action,
+    key: &Key<'_>,
+) -> anyhow::Result<()> {
+    sqlx::query!(
+        "delete from rate_limit_attempts where action = $1 and key = $2",
+        action.name(),
+        key.value(),
+    )
+    .execute(&mut **db)
+    .await?;
+    Ok(())
+}
+
+/// Deletes attempts which are older than every window and lockouts which are over, as most keys
+/// never come back.
+pub async fn purge_expired(db: &sqlx::PgPool) -> anyhow::Result<()> {
+    let longest_window = Action::ALL
+        .into_iter()
+        .flat_map(|action| {
+            [
+                action.limit(&Key::Ip(IpAddr::from([0, 0, 0, 0]))),
+                action.limit(&Key::Account("")),
+            ]
+        })
+        .map(|limit| limit.window_minutes)
+        .max()
+        .unwrap_or_default();
+    sqlx::query!(
+        "delete from rate_limit_attempts where created < now() - make_interval(mins => $1)",
+        longest_window,
+    )
+    .execute(db)
+    .await?;
+    sqlx::query!("delete from rate_limit_lockouts where until < now()")
+        .execute(db)
+        .await?;
+    Ok(())
+}
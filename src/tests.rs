@@ -0,0 +1,919 @@
+//! Tests of whole requests against the database configured in `Rocket.toml` or by
+//! `ROCKET_DATABASES`. Mails are kept by the stub transport.
+
+use lettre::transport::stub::AsyncStubTransport;
+use rocket::error::ErrorKind;
+use rocket::http::{ContentType, Cookie, Status};
+use rocket::local::asynchronous::{Client, LocalResponse};
+use rocket::tokio::{self, sync::Mutex};
+use rocket_db_pools::Database as DatabaseTrait;
+use sqlx::PgPool;
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use crate::auth::{hash_password, Role};
+use crate::{mail, Config, Database, Mailer};
+
+const DATE_TYPE: &str = "test-booking";
+const OTHER_DATE_TYPE: &str = "test-other";
+const ROOM: &str = "test-room";
+const EMAIL: &str = "candidate@tests.example.com";
+const ADMIN_EMAIL: &str = "admin@tests.example.com";
+const PASSWORD: &str = "Test-Passw0rd!";
+
+lazy_static! {
+    /// The tests share the database, whose outbox and jobs are not separated by test.
+    static ref SERIAL: Mutex<()> = Mutex::new(());
+}
+
+async fn client() -> Client {
+    // The jobs are run by the tests themselves, so they can't interfere with other tests.
+    let figment = rocket::Config::figment()
+        .merge(("email_transport", "stub"))
+        .merge(("disable_jobs", true));
+    Client::tracked(crate::rocket(figment))
+        .await
+        .expect("valid rocket instance")
+}
+
+fn database(client: &Client) -> PgPool {
+    Database::fetch(client.rocket())
+        .expect("database")
+        .0
+        .clone()
+}
+
+fn stub_messages_to(messages: &[(lettre::address::Envelope, String)], to: &str) -> Vec<String> {
+    messages
+        .iter()
+        .filter(|(envelope, _)| {
+            envelope
+                .to()
+                .iter()
+                .any(|address| address.to_string() == to)
+        })
+        .map(|(_, message)| message.clone())
+        .collect()
+}
+
+async fn delivered_to(client: &Client, to: &str) -> Vec<String> {
+    match client.rocket().state::<Mailer>().expect("mailer") {
+        Mailer::Stub(transport) => stub_messages_to(&transport.messages().await, to),
+        _ => panic!("The stub transport is not used!"),
+    }
+}
+
+fn csrf_token(body: &str) -> &str {
+    let start = body
+        .find(r#"name="csrf_token" value=""#)
+        .expect("csrf token")
+        + r#"name="csrf_token" value=""#.len();
+    let end = start + body[start..].find('"').expect("end of csrf token");
+    &body[start..end]
+}
+
+/// The token of the client's session, taken from the login form.
+async fn session_csrf_token(client: &Client) -> String {
+    let body = client
+        .get("/admin/login")
+        .dispatch()
+        .await
+        .into_string()
+        .await
+        .expect("body");
+    String::from(csrf_token(&body))
+}
+
+async fn post_form<'c>(client: &'c Client, uri: &str, body: String) -> LocalResponse<'c> {
+    client
+        .post(String::from(uri))
+        .cookie(Cookie::new("language", "en"))
+        .header(ContentType::Form)
+        .body(body)
+        .dispatch()
+        .await
+}
+
+/// Removes the data of previous runs, including the ones which failed.
+async fn clean_up(db: &PgPool) -> anyhow::Result<()> {
+    // Sessions, recovery codes and date type restrictions are deleted with the admins.
+    sqlx::query!("delete from admins where email like '%@tests.example.com'")
+        .execute(db)
+        .await?;
+    sqlx::query!("delete from invites where email like '%@tests.example.com'")
+        .execute(db)
+        .await?;
+    // Dates, bookings and voices are deleted with the date types.
+    sqlx::query!("delete from date_types where id like 'test-%'")
+        .execute(db)
+        .await?;
+    sqlx::query!("delete from announcements where position like 'test-%'")
+        .execute(db)
+        .await?;
+    sqlx::query!("delete from rooms where room_number like 'test-%'")
+        .execute(db)
+        .await?;
+    sqlx::query!("delete from mail_outbox where recipient like '%@tests.example.com'")
+        .execute(db)
+        .await?;
+    sqlx::query!("delete from rate_limit_attempts where key like 'account:%@tests.example.com'")
+        .execute(db)
+        .await?;
+    sqlx::query!("delete from rate_limit_lockouts where key like 'account:%@tests.example.com'")
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+/// Creates an open date type with the voice `tenor` and returns the id of the voice.
+async fn create_date_type(db: &PgPool, id: &str) -> anyhow::Result<i32> {
+    sqlx::query!(
+        "insert into date_types (id, enabled, dates_per_day) values ($1, true, 0)",
+        id
+    )
+    .execute(db)
+    .await?;
+    sqlx::query!(
+        "insert into date_types_translations (date_type, lang, display_name) \
+        values ($1, 'en', 'Test ensemble ' || $1), ($1, 'de', 'Testensemble ' || $1)",
+        id
+    )
+    .execute(db)
+    .await?;
+    sqlx::query!(
+        "insert into announcements (lang, position, description) \
+        values ('en', $1, ''), ('de', $1, '')",
+        id
+    )
+    .execute(db)
+    .await?;
+    let voice = sqlx::query_scalar!(
+        "insert into voices (value, date_type, position) values ('tenor', $1, 'booking') \
+        returning id",
+        id
+    )
+    .fetch_one(db)
+    .await?;
+    sqlx::query!(
+        "insert into voices_translations (voice, lang, display_name) \
+        values ($1, 'en', 'Tenor'), ($1, 'de', 'Tenor')",
+        voice
+    )
+    .execute(db)
+    .await?;
+    Ok(voice)
+}
+
+async fn create_room(db: &PgPool, room_number: &str) -> anyhow::Result<i32> {
+    Ok(sqlx::query_scalar!(
+        "insert into rooms (room_number) values ($1) returning id",
+        room_number
+    )
+    .fetch_one(db)
+    .await?)
+}
+
+/// Creates a date of 20 minutes of [`DATE_TYPE`] which starts `hours` from now, rounded down to
+/// the hour, and returns its id.
+async fn create_date_in(db: &PgPool, room: i32, hours: i32) -> anyhow::Result<i32> {
+    Ok(sqlx::query_scalar!(
+        "insert into dates (from_date, to_date, room_id, date_type) values \
+        (date_trunc('hour', now()) + make_interval(hours => $1), \
+        date_trunc('hour', now()) + make_interval(hours => $1, mins => 20), $2, $3) \
+        returning id",
+        hours,
+        room,
+        DATE_TYPE
+    )
+    .fetch_one(db)
+    .await?)
+}
+
+/// Creates an open date type with a single date in 30 days and returns the id of the date.
+async fn create_date(db: &PgPool) -> anyhow::Result<i32> {
+    create_date_type(db, DATE_TYPE).await?;
+    let room = create_room(db, ROOM).await?;
+    create_date_in(db, room, 30 * 24).await
+}
+
+async fn create_booking(
+    db: &PgPool,
+    date: i32,
+    voice: i32,
+    person_name: &str,
+    notes: &str,
+) -> anyhow::Result<String> {
+    Ok(sqlx::query_scalar!(
+        "insert into bookings (date_id, email, person_name, notes, voice, lang) \
+        values ($1, $2, $3, $4, $5, 'en') returning token",
+        date,
+        EMAIL,
+        person_name,
+        notes,
+        voice
+    )
+    .fetch_one(db)
+    .await?)
+}
+
+/// Creates an admin with [`PASSWORD`] who is restricted to `date_types` if there are any.
+async fn create_admin(
+    db: &PgPool,
+    email: &str,
+    role: Role,
+    date_types: &[&str],
+) -> anyhow::Result<String> {
+    let id = sqlx::query_scalar!(
+        "insert into admins (display_name, email, password, role) \
+        values ('Test Admin', $1, $2, $3) returning id",
+        email,
+        hash_password(PASSWORD)?,
+        role as Role
+    )
+    .fetch_one(db)
+    .await?;
+    for date_type in date_types {
+        sqlx::query!(
+            "insert into admins_date_types (admin_id, date_type) values ($1, $2)",
+            &id,
+            date_type
+        )
+        .execute(db)
+        .await?;
+    }
+    Ok(id)
+}
+
+async fn login<'c>(client: &'c Client, email: &str, password: &str) -> LocalResponse<'c> {
+    let token = session_csrf_token(client).await;
+    post_form(
+        client,
+        "/admin/login",
+        format!(
+            "csrf_token={}&email={}&password={}&remember=false",
+            token, email, password
+        ),
+    )
+    .await
+}
+
+#[rocket::async_test]
+async fn booking_sends_confirmation() -> anyhow::Result<()> {
+    let _serial = SERIAL.lock().await;
+    let client = client().await;
+    let db = database(&client);
+    clean_up(&db).await?;
+    let date = create_date(&db).await?;
+
+    let response = client
+        .get(format!("/booking/new/{}", date))
+        .cookie(Cookie::new("language", "en"))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    let body = response.into_string().await.expect("body");
+    let response = post_form(
+        &client,
+        &format!("/booking/new/{}", date),
+        format!(
+            "csrf_token={}&email={}&person_name=Test+Candidate&notes=&voice=tenor",
+            csrf_token(&body),
+            EMAIL
+        ),
+    )
+    .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    // The confirmation is queued together with the booking.
+    let queued = sqlx::query!(
+        "select subject, body from mail_outbox where recipient = $1",
+        EMAIL
+    )
+    .fetch_all(&db)
+    .await?;
+    assert_eq!(queued.len(), 1);
+    assert_eq!(queued[0].subject, "Audition date booking confirmation");
+    assert!(queued[0].body.contains(ROOM));
+
+    let config = client.rocket().state::<Config>().expect("config");
+    let mailer = client.rocket().state::<Mailer>().expect("mailer");
+    mail::deliver_mails(&db, config, mailer).await?;
+    let remaining = sqlx::query_scalar!(
+        r#"select count(*) as "count!" from mail_outbox where recipient = $1"#,
+        EMAIL
+    )
+    .fetch_one(&db)
+    .await?;
+    assert_eq!(remaining, 0);
+
+    let delivered = delivered_to(&client, EMAIL).await;
+    assert_eq!(delivered.len(), 1);
+    assert!(delivered[0].contains("Subject: Audition date booking confirmation"));
+    assert!(delivered[0].contains("Content-Type: text/calendar"));
+
+    clean_up(&db).await?;
+    Ok(())
+}
+
+#[rocket::async_test]
+async fn forms_without_csrf_token_are_rejected() {
+    let (client, other) = (client().await, client().await);
+    let token = session_csrf_token(&client).await;
+    let credentials = format!("email={}&password={}", ADMIN_EMAIL, PASSWORD);
+
+    let response = post_form(&client, "/admin/login", credentials.clone()).await;
+    assert_eq!(response.status(), Status::Forbidden);
+    let response = post_form(
+        &client,
+        "/admin/login",
+        format!("csrf_token={}0&{}", token, credentials),
+    )
+    .await;
+    assert_eq!(response.status(), Status::Forbidden);
+
+    // A token of another session doesn't work either.
+    let response = post_form(
+        &other,
+        "/admin/login",
+        format!("csrf_token={}&{}", token, credentials),
+    )
+    .await;
+    assert_eq!(response.status(), Status::Forbidden);
+}
+
+#[rocket::async_test]
+async fn logins_are_rate_limited() -> anyhow::Result<()> {
+    let _serial = SERIAL.lock().await;
+    let client = client().await;
+    let db = database(&client);
+    clean_up(&db).await?;
+    let admin = create_admin(&db, ADMIN_EMAIL, Role::Owner, &[]).await?;
+
+    for _ in 0..5 {
+        let response = login(&client, ADMIN_EMAIL, "Wr0ng-Passw0rd!").await;
+        assert_eq!(response.status(), Status::Ok);
+        let body = response.into_string().await.expect("body");
+        assert!(body.contains("E-Mail or password wrong!"));
+    }
+    // Even the right password is refused once the account is locked out.
+    let response = login(&client, ADMIN_EMAIL, PASSWORD).await;
+    assert_eq!(response.status(), Status::Ok);
+    let body = response.into_string().await.expect("body");
+    assert!(body.contains("There were too many attempts."));
+    let sessions = sqlx::query_scalar!(
+        r#"select count(*) as "count!" from sessions where admin_id = $1"#,
+        admin
+    )
+    .fetch_one(&db)
+    .await?;
+    assert_eq!(sessions, 0);
+
+    clean_up(&db).await?;
+    Ok(())
+}
+
+#[rocket::async_test]
+async fn bookings_are_rate_limited() -> anyhow::Result<()> {
+    let _serial = SERIAL.lock().await;
+    let client = client().await;
+    let db = database(&client);
+    clean_up(&db).await?;
+    create_date_type(&db, DATE_TYPE).await?;
+    let room = create_room(&db, ROOM).await?;
+    let token = session_csrf_token(&client).await;
+
+    for day in 0..6 {
+        let date = create_date_in(&db, room, (30 + day) * 24).await?;
+        let response = post_form(
+            &client,
+            &format!("/booking/new/{}", date),
+            format!(
+                "csrf_token={}&email={}&person_name=Test+Candidate&notes=&voice=tenor",
+                token, EMAIL
+            ),
+        )
+        .await;
+        let expected = if day < 5 {
+            Status::Ok
+        } else {
+            Status::TooManyRequests
+        };
+        assert_eq!(response.status(), expected);
+    }
+    let bookings = sqlx::query_scalar!(
+        r#"select count(*) as "count!" from bookings where email = $1"#,
+        EMAIL
+    )
+    .fetch_one(&db)
+    .await?;
+    assert_eq!(bookings, 5);
+
+    clean_up(&db).await?;
+    Ok(())
+}
+
+/// Logs in with the password and the code and returns the status of the code's response.
+async fn login_two_factor(client: &Client, code: &str) -> anyhow::Result<Status> {
+    let response = login(client, ADMIN_EMAIL, PASSWORD).await;
+    assert_eq!(response.status(), Status::SeeOther);
+    assert!(response
+        .headers()
+        .get_one("Location")
+        .is_some_and(|location| location.starts_with("/admin/login/two-factor")));
+    let token = session_csrf_token(client).await;
+    let response = post_form(
+        client,
+        "/admin/login/two-factor",
+        format!("csrf_token={}&code={}", token, code),
+    )
+    .await;
+    let status = response.status();
+    if status == Status::Ok {
+        let body = response.into_string().await.expect("body");
+        assert!(body.contains("The code is invalid or has already been used."));
+    }
+    client.get("/admin/logout").dispatch().await;
+    Ok(status)
+}
+
+#[rocket::async_test]
+async fn two_factor_codes_can_only_be_used_once() -> anyhow::Result<()> {
+    let _serial = SERIAL.lock().await;
+    let client = client().await;
+    let db = database(&client);
+    clean_up(&db).await?;
+    let admin = create_admin(&db, ADMIN_EMAIL, Role::Owner, &[]).await?;
+    let secret = "JBSWY3DPEHPK3PXPJBSWY3DPEHPK3PXP";
+    sqlx::query!(
+        "update admins set totp_secret = $2, totp_enabled = true where id = $1",
+        &admin,
+        secret
+    )
+    .execute(&db)
+    .await?;
+    let recovery_code = "abcde-fghij";
+    sqlx::query!(
+        "insert into recovery_codes (admin_id, code) values ($1, $2)",
+        &admin,
+        hash_password("abcdefghij")?
+    )
+    .execute(&db)
+    .await?;
+    let code = TOTP::new(
+        Algorithm::SHA1,
+        6,
+        1,
+        30,
+        Secret::Encoded(String::from(secret))
+            .to_bytes()
+            .expect("secret"),
+        Some(String::from("Audition Dates")),
+        String::from(ADMIN_EMAIL),
+    )?
+    .generate_current()?;
+
+    assert_eq!(login_two_factor(&client, &code).await?, Status::SeeOther);
+    assert_eq!(login_two_factor(&client, &code).await?, Status::Ok);
+    assert_eq!(
+        login_two_factor(&client, recovery_code).await?,
+        Status::SeeOther
+    );
+    assert_eq!(login_two_factor(&client, recovery_code).await?, Status::Ok);
+    let sessions = sqlx::query_scalar!(
+        r#"select count(*) as "count!" from audit_log where admin_id = $1 and action = 'login'"#,
+        admin
+    )
+    .fetch_one(&db)
+    .await?;
+    assert_eq!(sessions, 2);
+
+    clean_up(&db).await?;
+    Ok(())
+}
+
+#[rocket::async_test]
+async fn restricted_owners_only_manage_their_date_types() -> anyhow::Result<()> {
+    let _serial = SERIAL.lock().await;
+    let client = client().await;
+    let db = database(&client);
+    clean_up(&db).await?;
+    create_date_type(&db, DATE_TYPE).await?;
+    create_date_type(&db, OTHER_DATE_TYPE).await?;
+    create_admin(&db, ADMIN_EMAIL, Role::Owner, &[DATE_TYPE]).await?;
+    let unrestricted =
+        create_admin(&db, "unrestricted@tests.example.com", Role::Viewer, &[]).await?;
+    let other = create_admin(
+        &db,
+        "other@tests.example.com",
+        Role::Viewer,
+        &[OTHER_DATE_TYPE],
+    )
+    .await?;
+    let scoped = create_admin(&db, "scoped@tests.example.com", Role::Viewer, &[DATE_TYPE]).await?;
+    let other_invite = sqlx::query_scalar!(
+        "insert into invites (email, created, date_types) \
+        values ('invite-other@tests.example.com', now(), $1) returning token",
+        &[String::from(OTHER_DATE_TYPE)]
+    )
+    .fetch_one(&db)
+    .await?;
+    assert_eq!(
+        login(&client, ADMIN_EMAIL, PASSWORD).await.status(),
+        Status::SeeOther
+    );
+    let token = session_csrf_token(&client).await;
+
+    // Invites can't grant access to other date types and are restricted to the own ones.
+    let invited = "invited@tests.example.com";
+    let response = post_form(
+        &client,
+        "/admin/invite",
+        format!(
+            "csrf_token={}&email={}&role=viewer&date_types={}",
+            token, invited, OTHER_DATE_TYPE
+        ),
+    )
+    .await;
+    assert_eq!(response.status(), Status::Ok);
+    let body = response.into_string().await.expect("body");
+    assert!(body.contains("Please select an entry."));
+    let response = post_form(
+        &client,
+        "/admin/invite",
+        format!("csrf_token={}&email={}&role=viewer", token, invited),
+    )
+    .await;
+    assert_eq!(response.status(), Status::Ok);
+    let date_types =
+        sqlx::query_scalar!("select date_types from invites where email = $1", invited)
+            .fetch_one(&db)
+            .await?;
+    assert_eq!(date_types, [DATE_TYPE]);
+
+    for id in [&unrestricted, &other] {
+        let response = post_form(
+            &client,
+            "/admin/admins",
+            format!("csrf_token={}&button=delete-{}", token, id),
+        )
+        .await;
+        assert_eq!(response.status(), Status::Forbidden);
+    }
+    let response = post_form(
+        &client,
+        "/admin/admins",
+        format!("csrf_token={}&button=revoke-{}", token, other_invite),
+    )
+    .await;
+    assert_eq!(response.status(), Status::Forbidden);
+    let response = post_form(
+        &client,
+        "/admin/admins",
+        format!("csrf_token={}&button=delete-{}", token, scoped),
+    )
+    .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    // The audit log contains the actions on all date types.
+    let response = client.get("/admin/audit-log").dispatch().await;
+    assert_eq!(response.status(), Status::Forbidden);
+
+    let remaining = sqlx::query!(
+        r#"select
+        (select count(*) from admins where id = any($1)) as "admins!",
+        (select count(*) from invites where token = $2) as "invites!""#,
+        &[unrestricted, other, scoped],
+        other_invite
+    )
+    .fetch_one(&db)
+    .await?;
+    assert_eq!((remaining.admins, remaining.invites), (2, 1));
+
+    clean_up(&db).await?;
+    Ok(())
+}
+
+#[rocket::async_test]
+async fn export_escapes_fields() -> anyhow::Result<()> {
+    let _serial = SERIAL.lock().await;
+    let client = client().await;
+    let db = database(&client);
+    clean_up(&db).await?;
+    let voice = create_date_type(&db, DATE_TYPE).await?;
+    let room = create_room(&db, ROOM).await?;
+    let date = create_date_in(&db, room, 30 * 24).await?;
+    create_booking(&db, date, voice, "Doe, \"Jo\"", "=1+1\nsecond line").await?;
+    create_admin(&db, ADMIN_EMAIL, Role::Owner, &[]).await?;
+    login(&client, ADMIN_EMAIL, PASSWORD).await;
+
+    let response = client
+        .get(format!("/admin/export/dates.csv?date_type={}", DATE_TYPE))
+        .cookie(Cookie::new("language", "en"))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    let content = response.into_string().await.expect("body");
+    assert!(content.starts_with('\u{feff}'));
+    let records = crate::csv::read_records(&content, ',');
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[1][6], "Doe, \"Jo\"");
+    assert_eq!(records[1][9], "'=1+1\nsecond line");
+    assert!(content.contains(",\"Doe, \"\"Jo\"\"\","));
+
+    clean_up(&db).await?;
+    Ok(())
+}
+
+#[rocket::async_test]
+async fn import_previews_valid_rows_only() -> anyhow::Result<()> {
+    let _serial = SERIAL.lock().await;
+    let client = client().await;
+    let db = database(&client);
+    clean_up(&db).await?;
+    create_date_type(&db, DATE_TYPE).await?;
+    create_room(&db, ROOM).await?;
+    create_admin(&db, ADMIN_EMAIL, Role::Owner, &[]).await?;
+    login(&client, ADMIN_EMAIL, PASSWORD).await;
+    let token = session_csrf_token(&client).await;
+
+    let file = format!(
+        "Ensemble,Room,Start,End\r\n\
+        {0},{1},2031-03-01 10:00,2031-03-01 10:20\r\n\
+        \"{0}\",\"{1}\",\"01.03.2031 10:10\",\"01.03.2031 10:30\"\r\n\
+        {0},{1},2031-02-30 10:00,2031-03-01 10:20\r\n\
+        test-unknown,{1},2031-03-01 11:00,2031-03-01 11:20\r\n\
+        {0},{1},2031-03-01 12:00\r\n",
+        DATE_TYPE, ROOM
+    );
+    let body = format!(
+        "--boundary\r\n\
+        Content-Disposition: form-data; name=\"csrf_token\"\r\n\r\n\
+        {}\r\n\
+        --boundary\r\n\
+        Content-Disposition: form-data; name=\"file\"; filename=\"dates.csv\"\r\n\
+        Content-Type: text/csv\r\n\r\n\
+        {}\r\n\
+        --boundary--\r\n",
+        token, file
+    );
+    let response = client
+        .post("/admin/date-import")
+        .cookie(Cookie::new("language", "en"))
+        .header(ContentType::new("multipart", "form-data").with_params(("boundary", "boundary")))
+        .body(body)
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    let body = response.into_string().await.expect("body");
+    assert_eq!(body.matches(r#"value="true" checked"#).count(), 1);
+    assert!(body.contains("The date overlaps a date above in the same room."));
+    assert!(body.contains("The start or end can&#x27;t be read."));
+    assert!(body.contains("Unknown ensemble."));
+    assert!(body.contains("The row must have exactly four columns."));
+    // The dates are only created once they are confirmed.
+    let dates = sqlx::query_scalar!(
+        r#"select count(*) as "count!" from dates where date_type = $1"#,
+        DATE_TYPE
+    )
+    .fetch_one(&db)
+    .await?;
+    assert_eq!(dates, 0);
+
+    clean_up(&db).await?;
+    Ok(())
+}
+
+#[rocket::async_test]
+async fn calendar_feed_escapes_and_folds_lines() -> anyhow::Result<()> {
+    let _serial = SERIAL.lock().await;
+    let client = client().await;
+    let db = database(&client);
+    clean_up(&db).await?;
+    let voice = create_date_type(&db, DATE_TYPE).await?;
+    let room = create_room(&db, "test-room, first floor; left\\right").await?;
+    let date = create_date_in(&db, room, 30 * 24).await?;
+    let person_name = "Józefina Übermütig-Ängstlich von und zu Großschönau-Weißenhäuser";
+    create_booking(&db, date, voice, person_name, "").await?;
+    let admin = create_admin(&db, ADMIN_EMAIL, Role::Owner, &[]).await?;
+    sqlx::query!(
+        "update admins set calendar_token = 'test-calendar-token' where id = $1",
+        admin
+    )
+    .execute(&db)
+    .await?;
+
+    let response = client
+        .get("/calendar/test-calendar-token/dates.ics?lang=en")
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    let content = response.into_string().await.expect("body");
+    assert!(content.ends_with("END:VCALENDAR\r\n"));
+    for line in content.split("\r\n") {
+        assert!(line.len() <= 75, "{:?} is too long", line);
+    }
+    let unfolded = content.replace("\r\n ", "");
+    assert!(unfolded.contains("LOCATION:test-room\\, first floor\\; left\\\\right\r\n"));
+    assert!(unfolded.contains(person_name));
+
+    clean_up(&db).await?;
+    Ok(())
+}
+
+#[rocket::async_test]
+async fn reminders_are_sent_once() -> anyhow::Result<()> {
+    let _serial = SERIAL.lock().await;
+    let client = client().await;
+    let db = database(&client);
+    clean_up(&db).await?;
+    let voice = create_date_type(&db, DATE_TYPE).await?;
+    let room = create_room(&db, ROOM).await?;
+    // The reminder 48 hours before the date is due, the one 2 hours before isn't yet.
+    let date = create_date_in(&db, room, 24).await?;
+    let booking = create_booking(&db, date, voice, "Test Candidate", "").await?;
+    sqlx::query!(
+        "update bookings set created = now() - interval '3 days' where token = $1",
+        &booking
+    )
+    .execute(&db)
+    .await?;
+
+    let config = client.rocket().state::<Config>().expect("config");
+    let (mut first, mut second) = (db.acquire().await?, db.acquire().await?);
+    let (first, second) = tokio::join!(
+        mail::send_reminders(&mut first, config),
+        mail::send_reminders(&mut second, config)
+    );
+    first?;
+    second?;
+    mail::send_reminders(&mut *db.acquire().await?, config).await?;
+
+    let subjects = sqlx::query_scalar!(
+        "select subject from mail_outbox where recipient = $1",
+        EMAIL
+    )
+    .fetch_all(&db)
+    .await?;
+    assert_eq!(subjects, ["Reminder of your audition date"]);
+    let hours = sqlx::query_scalar!(
+        "select hours from booking_reminders where booking = $1",
+        &booking
+    )
+    .fetch_all(&db)
+    .await?;
+    assert_eq!(hours, [48]);
+
+    clean_up(&db).await?;
+    Ok(())
+}
+
+struct OutboxState {
+    attempts: i32,
+    given_up: bool,
+    backed_off: bool,
+    failed: bool,
+}
+
+async fn outbox_state(db: &PgPool, mail: i64) -> anyhow::Result<Option<OutboxState>> {
+    Ok(sqlx::query_as!(
+        OutboxState,
+        r#"select
+        attempts,
+        next_attempt is null as "given_up!",
+        coalesce(
+            next_attempt between now() + interval '50 seconds' and now() + interval '60 seconds',
+            false
+        ) as "backed_off!",
+        last_error is not null as "failed!"
+        from mail_outbox where id = $1"#,
+        mail
+    )
+    .fetch_optional(db)
+    .await?)
+}
+
+#[rocket::async_test]
+async fn failed_mails_are_retried_with_backoff() -> anyhow::Result<()> {
+    let _serial = SERIAL.lock().await;
+    let client = client().await;
+    let db = database(&client);
+    clean_up(&db).await?;
+    create_admin(&db, ADMIN_EMAIL, Role::Owner, &[]).await?;
+    let mail = sqlx::query_scalar!(
+        "insert into mail_outbox (recipient, subject, body) values ($1, 'Test', 'Test') \
+        returning id",
+        EMAIL
+    )
+    .fetch_one(&db)
+    .await?;
+    let config = client.rocket().state::<Config>().expect("config");
+    let failing = Mailer::Stub(AsyncStubTransport::new_error());
+    // The first retry is a minute later, until then the mail isn't tried again.
+    mail::deliver_mails(&db, config, &failing).await?;
+    mail::deliver_mails(&db, config, &failing).await?;
+    let record = outbox_state(&db, mail).await?.expect("queued mail");
+    assert_eq!(record.attempts, 1);
+    assert!(record.backed_off && record.failed && !record.given_up);
+
+    sqlx::query!(
+        "update mail_outbox set next_attempt = now(), attempts = 100 where id = $1",
+        mail
+    )
+    .execute(&db)
+    .await?;
+    mail::deliver_mails(&db, config, &failing).await?;
+    assert!(
+        outbox_state(&db, mail)
+            .await?
+            .expect("queued mail")
+            .given_up
+    );
+
+    // Mails which have been given up are sent again once an admin retries them.
+    assert_eq!(
+        login(&client, ADMIN_EMAIL, PASSWORD).await.status(),
+        Status::SeeOther
+    );
+    let token = session_csrf_token(&client).await;
+    let response = post_form(
+        &client,
+        "/admin/outbox",
+        format!("csrf_token={}&button=retry-{}", token, mail),
+    )
+    .await;
+    assert_eq!(response.status(), Status::Ok);
+    let mailer = client.rocket().state::<Mailer>().expect("mailer");
+    mail::deliver_mails(&db, config, mailer).await?;
+    assert!(outbox_state(&db, mail).await?.is_none());
+    assert_eq!(delivered_to(&client, EMAIL).await.len(), 1);
+
+    clean_up(&db).await?;
+    Ok(())
+}
+
+/// Inserts a date in 30 days at the given minutes after the full hour and returns the code of the
+/// database error if it is refused.
+async fn insert_date(db: &PgPool, room: i32, from_minutes: i32, to_minutes: i32) -> Option<String> {
+    match sqlx::query!(
+        "insert into dates (from_date, to_date, room_id, date_type) values \
+        (date_trunc('hour', now()) + make_interval(days => 30, mins => $1), \
+        date_trunc('hour', now()) + make_interval(days => 30, mins => $2), $3, $4)",
+        from_minutes,
+        to_minutes,
+        room,
+        DATE_TYPE
+    )
+    .execute(db)
+    .await
+    {
+        Ok(_) => None,
+        Err(sqlx::Error::Database(error)) => error.code().map(String::from),
+        Err(error) => panic!("{}", error),
+    }
+}
+
+#[rocket::async_test]
+async fn overlapping_dates_are_refused() -> anyhow::Result<()> {
+    let _serial = SERIAL.lock().await;
+    let client = client().await;
+    let db = database(&client);
+    clean_up(&db).await?;
+    create_date_type(&db, DATE_TYPE).await?;
+    let room = create_room(&db, ROOM).await?;
+    let other_room = create_room(&db, "test-other-room").await?;
+    create_date_in(&db, room, 30 * 24).await?;
+
+    assert_eq!(
+        insert_date(&db, room, 10, 30).await,
+        Some(String::from("23P01"))
+    );
+    assert_eq!(
+        insert_date(&db, room, -10, 5).await,
+        Some(String::from("23P01"))
+    );
+    assert_eq!(
+        insert_date(&db, room, 40, 30).await,
+        Some(String::from("23514"))
+    );
+    assert_eq!(insert_date(&db, room, 20, 40).await, None);
+    assert_eq!(insert_date(&db, room, -20, 0).await, None);
+    assert_eq!(insert_date(&db, other_room, 0, 20).await, None);
+
+    clean_up(&db).await?;
+    Ok(())
+}
+
+#[rocket::async_test]
+async fn moved_settings_are_refused() {
+    let figment = rocket::Config::figment()
+        .merge(("email_transport", "stub"))
+        .merge(("dates_per_day", 3));
+    let error = crate::rocket(figment)
+        .ignite()
+        .await
+        .expect_err("refused settings");
+    assert!(matches!(
+        error.kind(),
+        ErrorKind::FailedFairings(fairings)
+            if fairings.iter().any(|fairing| fairing.name == "Moved Settings")
+    ));
+}
@@ -0,0 +1,529 @@
+use std::net::IpAddr;
+
+use anyhow::anyhow;
+use chrono::Utc;
+use qrcode::render::svg;
+use qrcode::QrCode;
+use rand_core::{OsRng, RngCore};
+use rocket::http::{Cookie, CookieJar, Status};
+use rocket::response::Redirect;
+use rocket_db_pools::sqlx;
+use rocket_db_pools::Connection;
+use rocket_dyn_templates::{context, Template};
+use serde_json::json;
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use crate::audit;
+use crate::auth::{
+    hash_password, start_session, verify_password, LoggedIn, PendingLogin, Permission, UserAgent,
+    PENDING_LOGIN_COOKIE,
+};
+use crate::csrf::{constant_time_eq, CsrfForm, CsrfToken};
+use crate::language::Language;
+use crate::model::{Message, MessageType};
+use crate::rate_limit::{self, Action as RateLimitAction, Key};
+use crate::Database;
+use crate::RocketResult;
+
+const ISSUER: &str = "Audition Dates";
+const STEP: i64 = 30;
+const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+fn totp(secret: &str, account: &str) -> anyhow::Result<TOTP> {
+    let secret = Secret::Encoded(String::from(secret))
+        .to_bytes()
+        .map_err(|_| anyhow!("Invalid TOTP secret!"))?;
+    Ok(TOTP::new(
+        Algorithm::SHA1,
+        6,
+        1,
+        STEP as u64,
+        secret,
+        Some(String::from(ISSUER)),
+        account.replace(':', ""),
+    )?)
+}
+
+/// Returns the time step the code is valid for, allowing one step of clock skew in both directions.
+fn matching_step(totp: &TOTP, code: &str) -> Option<i64> {
+    let current = Utc::now().timestamp() / STEP;
+    (current - 1..=current + 1)
+        .find(|step| constant_time_eq(&totp.generate((step * STEP) as u64), code))
+}
+
+fn normalize_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+fn generate_recovery_code() -> String {
+    let mut bytes = [0u8; 10];
+    OsRng.fill_bytes(&mut bytes);
+    let code: String = bytes
+        .iter()
+        .map(|byte| RECOVERY_CODE_ALPHABET[(byte % 32) as usize] as char)
+        .collect();
+    format!("{}-{}", &code[..5], &code[5..])
+}
+
+/// Checks a TOTP code or an unused recovery code of the admin. Used codes are invalidated, so
+/// that an intercepted code cannot be replayed.
+async fn verify_code(
+    db: &mut Connection<Database>,
+    admin_id: &str,
+    code: &str,
+) -> anyhow::Result<bool> {
+    let code = normalize_code(code);
+    let record = sqlx::query!(
+        "select email, totp_secret from admins where id = $1",
+        admin_id
+    )
+    .fetch_one(&mut **db)
+    .await?;
+    let secret = match record.totp_secret {
+        Some(secret) => secret,
+        None => return Ok(false),
+    };
+
+    if let Some(step) = matching_step(&totp(&secret, &record.email)?, &code) {
+        let updated = sqlx::query!(
+            "update admins set totp_last_step = $2
+            where id = $1 and (totp_last_step is null or totp_last_step < $2)",
+            admin_id,
+            step,
+        )
+        .execute(&mut **db)
+        .await?;
+        return Ok(updated.rows_affected() == 1);
+    }
+
+    let recovery_codes = sqlx::query!(
+        "select id, code from recovery_codes where admin_id = $1",
+        admin_id
+    )
+    .fetch_all(&mut **db)
+    .await?;
+    for recovery_code in recovery_codes {
+        if verify_password(&code, &recovery_code.code)? {
+            // A concurrent request with the same code may have used it in the meantime.
+            let deleted =
+                sqlx::query!("delete from recovery_codes where id = $1", recovery_code.id)
+                    .execute(&mut **db)
+                    .await?;
+            return Ok(deleted.rows_affected() == 1);
+        }
+    }
+    Ok(false)
+}
+
+/// Replaces all recovery codes of the admin and returns the new ones in plain text.
+async fn renew_recovery_codes(
+    db: &mut Connection<Database>,
+    admin_id: &str,
+) -> anyhow::Result<Vec<String>> {
+    sqlx::query!("delete from recovery_codes where admin_id = $1", admin_id)
+        .execute(&mut **db)
+        .await?;
+    let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| generate_recovery_code())
+        .collect();
+    for code in &codes {
+        sqlx::query!(
+            "insert into recovery_codes (admin_id, code) values ($1, $2)",
+            admin_id,
+            hash_password(&normalize_code(code))?,
+        )
+        .execute(&mut **db)
+        .await?;
+    }
+    Ok(codes)
+}
+
+fn pending_login(cookies: &CookieJar<'_>) -> Option<PendingLogin> {
+    cookies
+        .get_private(PENDING_LOGIN_COOKIE)
+        .and_then(|cookie| serde_json::from_str::<PendingLogin>(cookie.value()).ok())
+        .filter(|pending| pending.valid_until > Utc::now())
+}
+
+#[get("/admin/login/two-factor?<redirect>")]
+pub async fn login_two_factor_get(
+    lang: Language,
+    csrf: CsrfToken,
+    cookies: &CookieJar<'_>,
+    redirect: Option<&str>,
+) -> Result<Template, Redirect> {
+    match pending_login(cookies) {
+        Some(_) => Ok(Template::render(
+            "login-two-factor",
+            context! { csrf_token: csrf.as_str(), lang: lang.into_string() },
+        )),
+        None => Err(Redirect::to(uri!(crate::auth::login_get(
+            redirect.map(String::from)
+        )))),
+    }
+}
+
+#[derive(FromForm)]
+pub struct TwoFactorCodeForm<'r> {
+    code: &'r str,
+}
+
+#[post("/admin/login/two-factor?<redirect>", data = "<form>")]
+#[allow(clippy::too_many_arguments)]
+pub async fn login_two_factor_post(
+    lang: Language,
+    csrf: CsrfToken,
+    mut db: Connection<Database>,
+    cookies: &CookieJar<'_>,
+    user_agent: UserAgent,
+    ip: Option<IpAddr>,
+    redirect: Option<&str>,
+    form: CsrfForm<TwoFactorCodeForm<'_>>,
+) -> RocketResult<Result<Redirect, (Status, Template)>> {
+    let pending = match pending_login(cookies) {
+        Some(pending) => pending,
+        None => {
+            return Ok(Ok(Redirect::to(uri!(crate::auth::login_get(
+                redirect.map(String::from)
+            )))))
+        }
+    };
+    // The password was already correct, so the codes are limited separately from the login.
+    let keys = rate_limit::keys(ip, &pending.admin);
+    if !rate_limit::attempt(&mut db, RateLimitAction::TwoFactor, &keys).await? {
+        return Ok(Err((
+            Status::TooManyRequests,
+            Template::render(
+                "login-two-factor",
+                context! {
+                    csrf_token: csrf.as_str(),
+                    lang: lang.into_string(),
+                    messages: [Message {
+                        text_key: String::from("rate-limited"),
+                        message_type: MessageType::Error,
+                    }],
+                },
+            ),
+        )));
+    }
+    if !verify_code(&mut db, &pending.admin, form.code).await? {
+        return Ok(Err((
+            Status::Ok,
+            Template::render(
+                "login-two-factor",
+                context! {
+                    csrf_token: csrf.as_str(),
+                    lang: lang.into_string(),
+                    messages: [Message {
+                        text_key: String::from("two-factor-invalid-code"),
+                        message_type: MessageType::Error,
+                    }],
+                },
+            ),
+        )));
+    }
+    rate_limit::clear(
+        &mut db,
+        RateLimitAction::TwoFactor,
+        &Key::Account(&pending.admin),
+    )
+    .await?;
+
+    cookies.remove_private(Cookie::named(PENDING_LOGIN_COOKIE));
+    start_session(
+        &mut db,
+        cookies,
+        &pending.admin,
+        pending.remember,
+        &user_agent,
+    )
+    .await?;
+    Ok(Ok(match redirect {
+        None => Redirect::to(uri!(crate::admin::dashboard(day = Option::<&str>::None))),
+        Some(redirect) => Redirect::to(String::from(redirect)),
+    }))
+}
+
+async fn render_two_factor(
+    lang: Language,
+    csrf: CsrfToken,
+    db: &mut Connection<Database>,
+    logged_in: &LoggedIn,
+    mut messages: Vec<Message>,
+    recovery_codes: Option<Vec<String>>,
+) -> anyhow::Result<Template> {
+    let admin = &logged_in.admin;
+    if logged_in.two_factor_missing {
+        messages.push(Message {
+            text_key: String::from("two-factor-required-info"),
+            message_type: MessageType::Info,
+        });
+    }
+    let record = sqlx::query!(
+        "select email, totp_secret, totp_enabled from admins where id = $1",
+        &admin.id
+    )
+    .fetch_one(&mut **db)
+    .await?;
+    let require_two_factor = sqlx::query_scalar!("select require_two_factor from settings")
+        .fetch_one(&mut **db)
+        .await?;
+    let remaining_recovery_codes = sqlx::query_scalar!(
+        r#"select count(*) as "count!" from recovery_codes where admin_id = $1"#,
+        &admin.id
+    )
+    .fetch_one(&mut **db)
+    .await?;
+
+    // A secret which is not enabled yet belongs to an unfinished enrollment and is shown again.
+    let enrollment = if record.totp_enabled {
+        None
+    } else {
+        let secret = match record.totp_secret {
+            Some(secret) => secret,
+            None => {
+                let mut bytes = [0u8; 20];
+                OsRng.fill_bytes(&mut bytes);
+                let secret = Secret::Raw(bytes.to_vec()).to_encoded().to_string();
+                sqlx::query!(
+                    "update admins set totp_secret = $2 where id = $1",
+                    &admin.id,
+                    &secret
+                )
+                .execute(&mut **db)
+                .await?;
+                secret
+            }
+        };
+        let url = totp(&secret, &record.email)?.get_url();
+        let qr_code = QrCode::new(url.as_bytes())?
+            .render::<svg::Color>()
+            .min_dimensions(200, 200)
+            .build();
+        Some(context! { secret, url, qr_code })
+    };
+
+    Ok(Template::render(
+        "two-factor",
+        context! {
+            csrf_token: csrf.as_str(),
+            lang: lang.into_string(),
+            enabled: record.totp_enabled,
+            enrollment,
+            recovery_codes,
+            remaining_recovery_codes,
+            require_two_factor,
+            manage_settings: admin.can(Permission::ManageAdmins),
+            messages,
+        },
+    ))
+}
+
+#[get("/admin/two-factor")]
+pub async fn two_factor_get(
+    lang: Language,
+    csrf: CsrfToken,
+    mut db: Connection<Database>,
+    logged_in: LoggedIn,
+) -> RocketResult<Template> {
+    Ok(render_two_factor(lang, csrf, &mut db, &logged_in, Vec::new(), None).await?)
+}
+
+#[derive(FromForm)]
+pub struct TwoFactorForm<'r> {
+    button: &'r str,
+    code: &'r str,
+    require_two_factor: bool,
+}
+
+#[post("/admin/two-factor", data = "<form>")]
+pub async fn two_factor_post(
+    lang: Language,
+    csrf: CsrfToken,
+    mut db: Connection<Database>,
+    logged_in: LoggedIn,
+    form: CsrfForm<TwoFactorForm<'_>>,
+) -> RocketResult<Result<Template, Status>> {
+    let admin = &logged_in.admin;
+    let mut messages = Vec::new();
+    let mut recovery_codes = None;
+    let invalid_code = Message {
+        text_key: String::from("two-factor-invalid-code"),
+        message_type: MessageType::Error,
+    };
+    match form.button {
+        "enable" => {
+            if verify_code(&mut db, &admin.id, form.code).await? {
+                sqlx::query!(
+                    "update admins set totp_enabled = true where id = $1",
+                    &admin.id
+                )
+                .execute(&mut *db)
+                .await?;
+                recovery_codes = Some(renew_recovery_codes(&mut db, &admin.id).await?);
+                audit::log(
+                    &mut db,
+                    Some(&admin.id),
+                    "two-factor-enable",
+                    std::slice::from_ref(&admin.id),
+                    json!({}),
+                )
+                .await?;
+                messages.push(Message {
+                    text_key: String::from("two-factor-enabled"),
+                    message_type: MessageType::Success,
+                });
+            } else {
+                messages.push(invalid_code);
+            }
+        }
+        "disable" => {
+            let required = sqlx::query_scalar!("select require_two_factor from settings")
+                .fetch_one(&mut *db)
+                .await?;
+            if required {
+                messages.push(Message {
+                    text_key: String::from("two-factor-cannot-disable"),
+                    message_type: MessageType::Error,
+                });
+            } else if verify_code(&mut db, &admin.id, form.code).await? {
+                sqlx::query!(
+                    "update admins set totp_secret = null, totp_enabled = false, totp_last_step = null
+                    where id = $1",
+                    &admin.id
+                )
+                .execute(&mut *db)
+                .await?;
+                sqlx::query!("delete from recovery_codes where admin_id = $1", &admin.id)
+                    .execute(&mut *db)
+                    .await?;
+                audit::log(
+                    &mut db,
+                    Some(&admin.id),
+                    "two-factor-disable",
+                    std::slice::from_ref(&admin.id),
+                    json!({}),
+                )
+                .await?;
+                messages.push(Message {
+                    text_key: String::from("two-factor-disabled"),
+                    message_type: MessageType::Success,
+                });
+            } else {
+                messages.push(invalid_code);
+            }
+        }
+        "recovery-codes" => {
+            if verify_code(&mut db, &admin.id, form.code).await? {
+                recovery_codes = Some(renew_recovery_codes(&mut db, &admin.id).await?);
+                audit::log(
+                    &mut db,
+                    Some(&admin.id),
+                    "recovery-codes-renew",
+                    std::slice::from_ref(&admin.id),
+                    json!({}),
+                )
+                .await?;
+            } else {
+                messages.push(invalid_code);
+            }
+        }
+        "settings" => {
+            if !admin.can(Permission::ManageAdmins) {
+                return Ok(Err(Status::Forbidden));
+            }
+            sqlx::query!(
+                "update settings set require_two_factor = $1",
+                form.require_two_factor
+            )
+            .execute(&mut *db)
+            .await?;
+            audit::log(
+                &mut db,
+                Some(&admin.id),
+                "settings-update",
+                &[],
+                json!({ "require_two_factor": form.require_two_factor }),
+            )
+            .await?;
+            messages.push(Message {
+                text_key: String::from("two-factor-settings-saved"),
+                message_type: MessageType::Success,
+            });
+        }
+        _ => messages.push(Message {
+            text_key: String::from("validation-unknown"),
+            message_type: MessageType::Error,
+        }),
+    }
+
+    // The guard has been evaluated before the change, so it may be out of date now.
+    let logged_in = LoggedIn {
+        admin: logged_in.admin.clone(),
+        two_factor_missing: sqlx::query_scalar!(
+            r#"select not admins.totp_enabled and settings.require_two_factor as "missing!"
+            from admins cross join settings where admins.id = $1"#,
+            &logged_in.admin.id
+        )
+        .fetch_one(&mut *db)
+        .await?,
+    };
+    Ok(Ok(render_two_factor(
+        lang,
+        csrf,
+        &mut db,
+        &logged_in,
+        messages,
+        recovery_codes,
+    )
+    .await?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_codes() {
+        assert_eq!(normalize_code(" 123 456 "), "123456");
+        assert_eq!(normalize_code("ABCDE-fghij"), "abcdefghij");
+        assert_eq!(normalize_code("-_ "), "");
+    }
+
+    #[test]
+    fn generates_recovery_codes_from_the_alphabet() {
+        let code = generate_recovery_code();
+        assert_eq!(code.len(), 11);
+        assert_eq!(code.as_bytes()[5], b'-');
+        assert!(code
+            .bytes()
+            .filter(|byte| *byte != b'-')
+            .all(|byte| RECOVERY_CODE_ALPHABET.contains(&byte)));
+        assert_eq!(normalize_code(&code).len(), 10);
+    }
+
+    #[test]
+    fn matches_codes_of_adjacent_steps_only() -> anyhow::Result<()> {
+        let totp = totp("JBSWY3DPEHPK3PXPJBSWY3DPEHPK3PXP", "admin:example.com")?;
+        let current = Utc::now().timestamp() / STEP;
+        // Even if the step ends during the test, its code is still accepted as the previous one.
+        assert_eq!(
+            matching_step(&totp, &totp.generate((current * STEP) as u64)),
+            Some(current)
+        );
+        assert_eq!(
+            matching_step(&totp, &totp.generate(((current + 3) * STEP) as u64)),
+            None
+        );
+        assert_eq!(
+            matching_step(&totp, &totp.generate(((current - 3) * STEP) as u64)),
+            None
+        );
+        assert_eq!(matching_step(&totp, "000000x"), None);
+        Ok(())
+    }
+}
@@ -1,8 +1,10 @@
+use std::net::IpAddr;
+
 use anyhow::Result;
+use chrono::Local;
 use map_macro::hash_map;
 use rocket::form::error::ErrorKind;
 use rocket::form::Contextual;
-use rocket::form::Form;
 use rocket::form::FromForm;
 use rocket::http::Status;
 use rocket::request::FromRequest;
@@ -11,34 +13,41 @@ use rocket::Request;
 use rocket::State;
 use rocket_db_pools::Connection;
 use rocket_dyn_templates::{context, Template};
+use serde_json::json;
+use sqlx::Acquire;
 use tera::Context;
 
+use crate::audit;
+use crate::csrf::{CsrfForm, CsrfToken, NoFields};
+use crate::ics;
 use crate::mail::send_mail;
+use crate::mail::send_mail_with_attachments;
 use crate::mail::waiting_list_notify;
+use crate::mail::CalendarBooking;
 use crate::mail::MailBody;
 use crate::model::check_date_type_access;
 use crate::model::get_announcement;
 use crate::model::get_waiting_list_email;
 use crate::model::Date;
+use crate::model::DateTypeSettings;
 use crate::model::Message;
 use crate::model::MessageType;
 use crate::model::SelectString;
 use crate::model::{DateType, Email};
-use crate::Mailer;
+use crate::rate_limit::{self, Action as RateLimitAction};
 use crate::{language::Language, Config, Database, RocketResult};
 
 #[get("/")]
-pub async fn index_get(
-    lang: Language,
-    mut db: Connection<Database>,
-    config: &State<Config>,
-) -> RocketResult<Template> {
+pub async fn index_get(lang: Language, mut db: Connection<Database>) -> RocketResult<Template> {
     let lang = lang.into_string();
     let announcement = get_announcement("general", &lang, &mut db).await?;
+    let enabled_date_types = sqlx::query_scalar!("select id from date_types where enabled")
+        .fetch_all(&mut *db)
+        .await?;
     let date_types: Vec<DateType> = DateType::get_variants(&mut db, &lang)
         .await?
         .into_iter()
-        .filter(|date_type| config.enabled_date_types.contains(&date_type.value))
+        .filter(|date_type| enabled_date_types.contains(&date_type.value))
         .collect();
     Ok(Template::render(
         "index",
@@ -53,29 +62,31 @@ pub async fn index_get(
 #[get("/dates/<date_type>?<token>")]
 pub async fn date_overview_get(
     lang: Language,
+    csrf: CsrfToken,
     mut db: Connection<Database>,
-    config: &State<Config>,
     date_type: &str,
     token: Option<&str>,
 ) -> RocketResult<Template> {
     let lang = lang.into_string();
-    let ignore_waiting_list = check_date_type_access(date_type, token, config, &mut db).await?;
-    let dates = Date::get_available_dates(
-        &mut db,
-        date_type,
-        config,
-        Some(lang.as_str()),
-        ignore_waiting_list,
-    )
-    .await?;
+    let ignore_waiting_list = check_date_type_access(date_type, token, &mut db).await?;
+    let dates =
+        Date::get_available_dates(&mut db, date_type, Some(lang.as_str()), ignore_waiting_list)
+            .await?;
+    // Tell people when they can come back if booking has not started yet.
+    let opens_at = DateTypeSettings::get(&mut db, date_type)
+        .await?
+        .opens_at
+        .filter(|opens_at| *opens_at > Local::now());
     let announcement = get_announcement(date_type, &lang, &mut db).await?;
     let date_type = DateType::get_by_value(&mut db, date_type, &lang).await?;
     Ok(Template::render(
         "date-overview",
         context! {
+            csrf_token: csrf.as_str(),
             lang,
             date_type,
             dates,
+            opens_at,
             announcement,
             token,
         },
@@ -95,13 +106,13 @@ pub struct BookingForm<'r> {
 #[get("/booking/new/<id>?<token>")]
 pub async fn booking_new_get(
     lang: Language,
+    csrf: CsrfToken,
     mut db: Connection<Database>,
-    config: &State<Config>,
     id: i32,
     token: Option<&str>,
 ) -> RocketResult<Result<Template, Status>> {
     let lang = lang.into_string();
-    let date = Date::get_available_date(&mut db, id, lang.as_str(), config, token).await?;
+    let date = Date::get_available_date(&mut db, id, lang.as_str(), token).await?;
 
     match date {
         None => Ok(Err(Status::Gone)),
@@ -113,6 +124,7 @@ pub async fn booking_new_get(
             Ok(Ok(Template::render(
                 "booking-new",
                 context! {
+                    csrf_token: csrf.as_str(),
                     lang,
                     voices,
                     date,
@@ -130,17 +142,19 @@ pub async fn booking_new_get(
 }
 
 #[post("/booking/new/<id>", data = "<form>")]
+#[allow(clippy::too_many_arguments)]
 pub async fn booking_new_post(
     lang: Language,
+    csrf: CsrfToken,
     mut db: Connection<Database>,
     config: &State<Config>,
-    mailer: &State<Mailer>,
-    form: Form<Contextual<'_, BookingForm<'_>>>,
+    form: CsrfForm<Contextual<'_, BookingForm<'_>>>,
+    ip: Option<IpAddr>,
     id: i32,
 ) -> RocketResult<Result<Template, Status>> {
     let lang = lang.into_string();
     let token = form.context.field_value("token");
-    let date = match Date::get_available_date(&mut db, id, lang.as_str(), config, token).await? {
+    let date = match Date::get_available_date(&mut db, id, lang.as_str(), token).await? {
         Some(date) => date,
         None => {
             return Ok(Err(Status::Gone));
@@ -169,6 +183,7 @@ pub async fn booking_new_post(
             Ok(Ok(Template::render(
                 "booking-new",
                 context! {
+                    csrf_token: csrf.as_str(),
                     lang,
                     voices,
                     date,
@@ -197,6 +212,12 @@ pub async fn booking_new_post(
                 }
             }
 
+            let keys = rate_limit::keys(ip, email);
+            if !rate_limit::attempt(&mut db, RateLimitAction::Booking, &keys).await? {
+                return Ok(Err(Status::TooManyRequests));
+            }
+
+            let mut tx = db.begin().await?;
             let token = sqlx::query_scalar!(
                 "insert into bookings (date_id, email, person_name, notes, voice, lang) \
             values ($1, $2, $3, $4, (select id from voices where value = $5 and date_type = $6 and position = 'booking'), $7) \
@@ -209,7 +230,20 @@ pub async fn booking_new_post(
                 &date.date_type.value,
                 &lang,
             )
-            .fetch_one(&mut *db)
+            .fetch_one(&mut *tx)
+            .await?;
+            audit::log(
+                &mut tx,
+                None,
+                "booking-create",
+                std::slice::from_ref(&token),
+                json!({
+                    "date_id": date.id,
+                    "email": email,
+                    "person_name": person_name,
+                    "voice": voice,
+                }),
+            )
             .await?;
 
             sqlx::query!(
@@ -219,13 +253,26 @@ pub async fn booking_new_post(
                 &email,
                 &date.date_type.value
             )
-            .execute(&mut *db)
+            .execute(&mut *tx)
             .await?;
 
             let link = format!("{}/booking/delete/{}", &config.web_address, &token);
-            send_mail(
+            let calendar = CalendarBooking {
+                token: &token,
+                from_date: date.from_date,
+                to_date: date.to_date,
+                room_number: &date.room_number,
+                date_type: date
+                    .date_type
+                    .display_name
+                    .as_deref()
+                    .unwrap_or(&date.date_type.value),
+                description: &announcement,
+            }
+            .attachment(config, email, &lang, ics::Method::Publish)?;
+            send_mail_with_attachments(
+                &mut tx,
                 config,
-                mailer,
                 email,
                 &lang,
                 "mail-booking-subject",
@@ -242,8 +289,10 @@ pub async fn booking_new_post(
                         announcement: &announcement,
                     })?,
                 ),
+                vec![calendar],
             )
             .await?;
+            tx.commit().await?;
 
             Ok(Ok(Template::render(
                 "booking-success",
@@ -266,40 +315,122 @@ pub async fn date_gone_handler(req: &Request<'_>) -> Template {
     )
 }
 
+#[catch(429)]
+pub async fn too_many_requests_handler(req: &Request<'_>) -> Template {
+    let lang = Language::from_request(req).await.unwrap().into_string();
+    Template::render(
+        "too-many-requests",
+        context! {
+            lang
+        },
+    )
+}
+
 #[get("/booking/delete/<_token>")]
-pub async fn booking_delete_get(lang: Language, _token: &str) -> Template {
-    Template::render("booking-delete", context! { lang: lang.into_string() })
+pub async fn booking_delete_get(lang: Language, csrf: CsrfToken, _token: &str) -> Template {
+    Template::render(
+        "booking-delete",
+        context! { csrf_token: csrf.as_str(), lang: lang.into_string() },
+    )
 }
 
-#[post("/booking/delete/<token>")]
+#[post("/booking/delete/<token>", data = "<_form>")]
 pub async fn booking_delete_post(
     lang: Language,
+    csrf: CsrfToken,
     mut db: Connection<Database>,
     config: &State<Config>,
-    mailer: &State<Mailer>,
     token: &str,
+    _form: CsrfForm<NoFields>,
 ) -> RocketResult<Template> {
-    let record = sqlx::query!(
-        r#"select from_date < now() as "too_late!", date_type
+    let booking = sqlx::query!(
+        r#"select
+        dates.from_date < now() as "too_late!",
+        dates.from_date,
+        dates.to_date,
+        dates.date_type,
+        rooms.room_number,
+        bookings.email,
+        bookings.lang
         from dates
         join bookings on dates.id = bookings.date_id
+        join rooms on rooms.id = dates.room_id
         where bookings.token = $1"#,
         &token
     )
-    .map(|record| (record.too_late, record.date_type))
     .fetch_optional(&mut *db)
     .await?;
 
-    match record {
-        Some((true, _)) => Ok(Template::render(
+    match booking {
+        Some(booking) if booking.too_late => Ok(Template::render(
             "booking-delete",
-            context! { lang: lang.into_string(), messages: [Message { text_key: String::from("booking-delete-too-late"), message_type: MessageType::Error }] },
+            context! {
+                csrf_token: csrf.as_str(),
+                lang: lang.into_string(),
+                messages: [Message { text_key: String::from("booking-delete-too-late"), message_type: MessageType::Error }],
+            },
         )),
-        Some((false, date_type)) => {
-            sqlx::query!("delete from bookings where token = $1", &token)
-                .execute(&mut *db)
-                .await?;
-            waiting_list_notify(&mut db, &date_type, config, mailer).await?;
+        Some(booking) => {
+            let mut tx = db.begin().await?;
+            let deleted = sqlx::query!(
+                "delete from bookings where token = $1 returning date_id, email, person_name",
+                &token
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+            audit::log(
+                &mut tx,
+                None,
+                "booking-delete",
+                &[String::from(token)],
+                json!({
+                    "date_id": deleted.date_id,
+                    "email": deleted.email,
+                    "person_name": deleted.person_name,
+                }),
+            )
+            .await?;
+
+            // The candidate's calendar entry is removed by a cancellation.
+            let date_type =
+                DateType::get_by_value(&mut tx, &booking.date_type, &booking.lang).await?;
+            let from_date = booking.from_date.with_timezone(&Local);
+            let to_date = booking.to_date.with_timezone(&Local);
+            let calendar = CalendarBooking {
+                token,
+                from_date,
+                to_date,
+                room_number: &booking.room_number,
+                date_type: date_type
+                    .display_name
+                    .as_deref()
+                    .unwrap_or(&date_type.value),
+                description: "",
+            }
+            .attachment(config, &booking.email, &booking.lang, ics::Method::Cancel)?;
+            send_mail_with_attachments(
+                &mut tx,
+                config,
+                &booking.email,
+                &booking.lang,
+                "mail-booking-cancelled-subject",
+                None,
+                MailBody::Template(
+                    "booking-cancelled.tera",
+                    &Context::from_serialize(context! {
+                        lang: &booking.lang,
+                        day: format!("{}", from_date.naive_local().format("%d.%m.%Y")),
+                        from: format!("{}", from_date.naive_local().format("%H:%M")),
+                        to: format!("{}", to_date.naive_local().format("%H:%M")),
+                        room_number: &booking.room_number,
+                    })?,
+                ),
+                vec![calendar],
+            )
+            .await?;
+
+            waiting_list_notify(&mut tx, &booking.date_type, config).await?;
+            tx.commit().await?;
             Ok(Template::render(
                 "booking-delete-confirm",
                 context! { lang: lang.into_string() },
@@ -322,12 +453,17 @@ pub async fn waiting_list_subscribe_post(
     lang: Language,
     mut db: Connection<Database>,
     config: &State<Config>,
-    mailer: &State<Mailer>,
+    ip: Option<IpAddr>,
     date_type: &str,
-    form: Form<WaitingListForm<'_>>,
-) -> RocketResult<Template> {
+    form: CsrfForm<WaitingListForm<'_>>,
+) -> RocketResult<Result<Template, Status>> {
     let email = form.into_inner().email.0;
+    let keys = rate_limit::keys(ip, email);
+    if !rate_limit::attempt(&mut db, RateLimitAction::WaitingList, &keys).await? {
+        return Ok(Err(Status::TooManyRequests));
+    }
     let lang = lang.into_string();
+    let mut tx = db.begin().await?;
     let token = sqlx::query_scalar!(
         r#"insert into waiting_list (date_type, email, lang)
         values  ($1, $2, $3)
@@ -337,7 +473,7 @@ pub async fn waiting_list_subscribe_post(
         &email,
         &lang
     )
-    .fetch_optional(&mut *db)
+    .fetch_optional(&mut *tx)
     .await?;
     let token = match token {
         Some(token) => token,
@@ -347,17 +483,17 @@ pub async fn waiting_list_subscribe_post(
                 &date_type,
                 &email
             )
-            .fetch_one(&mut *db)
+            .fetch_one(&mut *tx)
             .await?
         }
     };
-    let date_type = DateType::get_by_value(&mut db, date_type, &lang).await?;
+    let date_type = DateType::get_by_value(&mut tx, date_type, &lang).await?;
     let subject_args = hash_map! {
         "datetype" => date_type.display_name.as_deref().unwrap()
     };
     send_mail(
+        &mut tx,
         config,
-        mailer,
         email,
         &lang,
         "waiting-list",
@@ -374,15 +510,17 @@ pub async fn waiting_list_subscribe_post(
         ),
     )
     .await?;
-    Ok(Template::render(
+    tx.commit().await?;
+    Ok(Ok(Template::render(
         "waiting-list-confirmation",
         context! { lang, date_type },
-    ))
+    )))
 }
 
 #[get("/waiting-list/unsubscribe/<token>")]
 pub async fn waiting_list_unsubscribe_get(
     lang: Language,
+    csrf: CsrfToken,
     mut db: Connection<Database>,
     token: &str,
 ) -> RocketResult<Template> {
@@ -401,15 +539,17 @@ pub async fn waiting_list_unsubscribe_get(
     Ok(Template::render(
         "waiting-list-unsubscribe",
         context! {
+            csrf_token: csrf.as_str(),
             lang, date_type
         },
     ))
 }
 
-#[post("/waiting-list/unsubscribe/<token>")]
+#[post("/waiting-list/unsubscribe/<token>", data = "<_form>")]
 pub async fn waiting_list_unsubscribe_post(
     mut db: Connection<Database>,
     token: &str,
+    _form: CsrfForm<NoFields>,
 ) -> RocketResult<Redirect> {
     sqlx::query!(
         r#"delete from waiting_list